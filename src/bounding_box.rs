@@ -0,0 +1,402 @@
+use crate::point3::*;
+use crate::ray::*;
+use crate::matrix::matrix4::Matrix4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundingBox {
+    pub min: Point3,
+    pub max: Point3
+}
+
+impl BoundingBox {
+    pub fn new(min: Point3, max: Point3) -> BoundingBox {
+        BoundingBox {
+            min,
+            max
+        }
+    }
+
+    // Contains nothing; adding the first point collapses it onto that point.
+    pub fn empty() -> BoundingBox {
+        BoundingBox::new(
+            Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY)
+        )
+    }
+
+    pub fn infinite() -> BoundingBox {
+        BoundingBox::new(
+            Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY)
+        )
+    }
+
+    // True when min exceeds max on some axis, as for BoundingBox::empty().
+    pub fn is_empty(&self) -> bool {
+        self.min.0 > self.max.0 || self.min.1 > self.max.1 || self.min.2 > self.max.2
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [self.min.0, self.min.1, self.min.2, self.max.0, self.max.1, self.max.2]
+            .iter()
+            .all(|value| value.is_finite())
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            (self.min.0 + self.max.0) / 2.0,
+            (self.min.1 + self.max.1) / 2.0,
+            (self.min.2 + self.max.2) / 2.0
+        )
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let extent = self.max - self.min;
+        if extent.0 < 0.0 || extent.1 < 0.0 || extent.2 < 0.0 {
+            return 0.0;
+        }
+        2.0 * (extent.0 * extent.1 + extent.1 * extent.2 + extent.2 * extent.0)
+    }
+
+    pub fn add_point(&mut self, point: Point3) {
+        self.min = Point3::new(
+            self.min.0.min(point.0),
+            self.min.1.min(point.1),
+            self.min.2.min(point.2)
+        );
+        self.max = Point3::new(
+            self.max.0.max(point.0),
+            self.max.1.max(point.1),
+            self.max.2.max(point.2)
+        );
+    }
+
+    // Merges bounds axis by axis, so an empty box leaves the other unchanged.
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox::new(
+            Point3::new(
+                self.min.0.min(other.min.0),
+                self.min.1.min(other.min.1),
+                self.min.2.min(other.min.2)
+            ),
+            Point3::new(
+                self.max.0.max(other.max.0),
+                self.max.1.max(other.max.1),
+                self.max.2.max(other.max.2)
+            )
+        )
+    }
+
+    pub fn contains_point(&self, point: Point3) -> bool {
+        self.min.0 <= point.0 && point.0 <= self.max.0 &&
+        self.min.1 <= point.1 && point.1 <= self.max.1 &&
+        self.min.2 <= point.2 && point.2 <= self.max.2
+    }
+
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    // Transforms all eight corners and boxes the result. Empty boxes stay
+    // empty and unbounded boxes stay unbounded, since infinite coordinates
+    // can't be pushed through a matrix.
+    pub fn transform(&self, matrix: Matrix4) -> BoundingBox {
+        if self.is_empty() {
+            return BoundingBox::empty();
+        }
+        if !self.is_finite() {
+            return BoundingBox::infinite();
+        }
+        let corners = [
+            Point3::new(self.min.0, self.min.1, self.min.2),
+            Point3::new(self.min.0, self.min.1, self.max.2),
+            Point3::new(self.min.0, self.max.1, self.min.2),
+            Point3::new(self.min.0, self.max.1, self.max.2),
+            Point3::new(self.max.0, self.min.1, self.min.2),
+            Point3::new(self.max.0, self.min.1, self.max.2),
+            Point3::new(self.max.0, self.max.1, self.min.2),
+            Point3::new(self.max.0, self.max.1, self.max.2)
+        ];
+        let mut transformed = BoundingBox::empty();
+        for corner in corners.iter() {
+            transformed.add_point(matrix * *corner);
+        }
+        transformed
+    }
+
+    // No epsilon here: tiny directions are common inside heavily scaled
+    // instances. A zero direction divides out to infinities, which already
+    // give the whole line for a ray inside the slab. The exceptions are an
+    // origin on a face, where 0 / 0 is NaN, and a ray outside the slab, whose
+    // two planes are both at the same infinity.
+    fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
+        let outside = (f64::INFINITY, f64::NEG_INFINITY);
+        let t1 = (min - origin) / direction;
+        let t2 = (max - origin) / direction;
+        if t1.is_nan() || t2.is_nan() {
+            return if direction == 0.0 && min <= origin && origin <= max {
+                (f64::NEG_INFINITY, f64::INFINITY)
+            } else {
+                outside
+            };
+        }
+        let (tmin, tmax) = if t1 > t2 { (t2, t1) } else { (t1, t2) };
+        if tmin == tmax && tmin.is_infinite() {
+            return outside;
+        }
+        (tmin, tmax)
+    }
+
+    // The range of t over which the ray is inside the box, if it meets it at all.
+    pub fn intersection_range(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let (xtmin, xtmax) = BoundingBox::check_axis(
+            ray.origin.0, ray.direction.0, self.min.0, self.max.0
+        );
+        let (ytmin, ytmax) = BoundingBox::check_axis(
+            ray.origin.1, ray.direction.1, self.min.1, self.max.1
+        );
+        let (ztmin, ztmax) = BoundingBox::check_axis(
+            ray.origin.2, ray.direction.2, self.min.2, self.max.2
+        );
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        if tmin <= tmax {
+            Some((tmin, tmax))
+        } else {
+            None
+        }
+    }
+
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        self.intersection_range(ray).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector3::Vector3;
+
+    fn rotation_y(radians: f64) -> Matrix4 {
+        Matrix4::new([
+            radians.cos(), 0.0, radians.sin(), 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            -radians.sin(), 0.0, radians.cos(), 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    fn rotation_x(radians: f64) -> Matrix4 {
+        Matrix4::new([
+            1.0, 0.0, 0.0, 0.0,
+            0.0, radians.cos(), -radians.sin(), 0.0,
+            0.0, radians.sin(), radians.cos(), 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    #[test]
+    fn creating_an_empty_bounding_box() {
+        let b = BoundingBox::empty();
+
+        assert_eq!(b.min.0, f64::INFINITY);
+        assert_eq!(b.max.2, f64::NEG_INFINITY);
+        assert!(!b.contains_point(Point3::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn adding_points_to_an_empty_bounding_box() {
+        let mut b = BoundingBox::empty();
+        b.add_point(Point3::new(-5.0, 2.0, 0.0));
+        b.add_point(Point3::new(7.0, 0.0, -3.0));
+
+        assert_eq!(b.min, Point3::new(-5.0, 0.0, -3.0));
+        assert_eq!(b.max, Point3::new(7.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn union_of_two_bounding_boxes() {
+        let b1 = BoundingBox::new(Point3::new(-5.0, -2.0, 0.0), Point3::new(7.0, 4.0, 4.0));
+        let b2 = BoundingBox::new(Point3::new(8.0, -7.0, -2.0), Point3::new(14.0, 2.0, 8.0));
+        let merged = b1.union(&b2);
+
+        assert_eq!(merged.min, Point3::new(-5.0, -7.0, -2.0));
+        assert_eq!(merged.max, Point3::new(14.0, 4.0, 8.0));
+    }
+
+    #[test]
+    fn centroid_and_surface_area_of_a_box() {
+        let b = BoundingBox::new(Point3::new(-1.0, 0.0, 2.0), Point3::new(3.0, 2.0, 3.0));
+
+        assert_eq!(b.centroid(), Point3::new(1.0, 1.0, 2.5));
+        assert_eq!(b.surface_area(), 28.0);
+        assert_eq!(BoundingBox::empty().surface_area(), 0.0);
+    }
+
+    #[test]
+    fn checking_whether_a_box_contains_a_point() {
+        let b = BoundingBox::new(Point3::new(5.0, -2.0, 0.0), Point3::new(11.0, 4.0, 7.0));
+
+        assert!(b.contains_point(Point3::new(5.0, -2.0, 0.0)));
+        assert!(b.contains_point(Point3::new(11.0, 4.0, 7.0)));
+        assert!(b.contains_point(Point3::new(8.0, 1.0, 3.0)));
+        assert!(!b.contains_point(Point3::new(3.0, 0.0, 3.0)));
+        assert!(!b.contains_point(Point3::new(8.0, -4.0, 3.0)));
+        assert!(!b.contains_point(Point3::new(8.0, 1.0, -1.0)));
+        assert!(!b.contains_point(Point3::new(13.0, 1.0, 3.0)));
+        assert!(!b.contains_point(Point3::new(8.0, 5.0, 3.0)));
+        assert!(!b.contains_point(Point3::new(8.0, 1.0, 8.0)));
+    }
+
+    #[test]
+    fn checking_whether_a_box_contains_another_box() {
+        let b = BoundingBox::new(Point3::new(5.0, -2.0, 0.0), Point3::new(11.0, 4.0, 7.0));
+
+        assert!(b.contains_box(&BoundingBox::new(
+            Point3::new(5.0, -2.0, 0.0), Point3::new(11.0, 4.0, 7.0)
+        )));
+        assert!(b.contains_box(&BoundingBox::new(
+            Point3::new(6.0, -1.0, 1.0), Point3::new(10.0, 3.0, 6.0)
+        )));
+        assert!(!b.contains_box(&BoundingBox::new(
+            Point3::new(4.0, -3.0, -1.0), Point3::new(10.0, 3.0, 6.0)
+        )));
+        assert!(!b.contains_box(&BoundingBox::new(
+            Point3::new(6.0, -1.0, 1.0), Point3::new(12.0, 5.0, 8.0)
+        )));
+    }
+
+    #[test]
+    fn transforming_a_bounding_box() {
+        let b = BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let matrix = rotation_x(std::f64::consts::PI / 4.0) * rotation_y(std::f64::consts::PI / 4.0);
+        let transformed = b.transform(matrix);
+
+        assert_eq!(transformed.min, Point3::new(-std::f64::consts::SQRT_2, -1.70710, -1.70710));
+        assert_eq!(transformed.max, Point3::new(std::f64::consts::SQRT_2, 1.70710, 1.70710));
+    }
+
+    #[test]
+    fn transforming_an_unbounded_box_stays_unbounded() {
+        let plane = BoundingBox::new(
+            Point3::new(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            Point3::new(f64::INFINITY, 0.0, f64::INFINITY)
+        );
+
+        assert!(!plane.transform(rotation_x(1.0)).is_finite());
+        assert!(BoundingBox::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0)).is_finite());
+    }
+
+    #[test]
+    fn transforming_an_empty_box_stays_empty() {
+        let transformed = BoundingBox::empty().transform(rotation_x(1.0));
+
+        assert!(transformed.is_empty());
+        assert_eq!(transformed.min.0, f64::INFINITY);
+        assert_eq!(transformed.max.0, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn an_empty_box_leaves_a_union_unchanged() {
+        let b = BoundingBox::new(Point3::new(-1.0, 0.0, 2.0), Point3::new(1.0, 3.0, 4.0));
+
+        assert_eq!(b.union(&BoundingBox::empty()), b);
+        assert_eq!(BoundingBox::empty().union(&b), b);
+        assert!(BoundingBox::empty().union(&BoundingBox::empty()).is_empty());
+        assert!(!b.is_empty());
+    }
+
+    #[test]
+    fn rays_parallel_to_a_face_plane() {
+        let b = BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        // The origin lies exactly on the min and max planes of the x slab.
+        let on_min_face = Ray::new(Point3::new(-1.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let on_max_face = Ray::new(Point3::new(1.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let outside = Ray::new(Point3::new(1.5, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(b.intersection_range(&on_min_face), Some((4.0, 6.0)));
+        assert_eq!(b.intersection_range(&on_max_face), Some((4.0, 6.0)));
+        assert_eq!(b.intersection_range(&outside), None);
+    }
+
+    #[test]
+    fn tiny_directions_are_not_treated_as_parallel() {
+        let b = BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        // What a unit ray looks like inside an instance scaled up a million times.
+        let slow = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1e-6));
+        let slow_diagonal = Ray::new(Point3::new(-5.0, 0.0, -5.0), Vector3::new(1e-6, 0.0, 1e-6));
+        let (tmin, tmax) = b.intersection_range(&slow).unwrap();
+
+        assert!((tmin - 4e6).abs() < 1e-3 && (tmax - 6e6).abs() < 1e-3);
+        assert!(b.intersects(&slow_diagonal));
+    }
+
+    #[test]
+    fn rays_with_nan_directions_miss() {
+        let b = BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(f64::NAN, 0.0, 1.0));
+
+        assert_eq!(b.intersection_range(&r), None);
+        assert!(!BoundingBox::infinite().intersects(&r));
+    }
+
+    #[test]
+    fn intersecting_a_ray_with_a_cubic_bounding_box() {
+        let b = BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let cases = [
+            (Point3::new(5.0, 0.5, 0.0), Vector3::new(-1.0, 0.0, 0.0), true),
+            (Point3::new(-5.0, 0.5, 0.0), Vector3::new(1.0, 0.0, 0.0), true),
+            (Point3::new(0.5, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0), true),
+            (Point3::new(0.5, -5.0, 0.0), Vector3::new(0.0, 1.0, 0.0), true),
+            (Point3::new(0.5, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0), true),
+            (Point3::new(0.5, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0), true),
+            (Point3::new(0.0, 0.5, 0.0), Vector3::new(0.0, 0.0, 1.0), true),
+            (Point3::new(-2.0, 0.0, 0.0), Vector3::new(2.0, 4.0, 6.0), false),
+            (Point3::new(0.0, -2.0, 0.0), Vector3::new(6.0, 2.0, 4.0), false),
+            (Point3::new(0.0, 0.0, -2.0), Vector3::new(4.0, 6.0, 2.0), false),
+            (Point3::new(2.0, 0.0, 2.0), Vector3::new(0.0, 0.0, -1.0), false),
+            (Point3::new(0.0, 2.0, 2.0), Vector3::new(0.0, -1.0, 0.0), false),
+            (Point3::new(2.0, 2.0, 0.0), Vector3::new(-1.0, 0.0, 0.0), false)
+        ];
+
+        for (origin, direction, expected) in cases.iter() {
+            let r = Ray::new(*origin, direction.normalize());
+            assert_eq!(b.intersects(&r), *expected);
+        }
+    }
+
+    #[test]
+    fn the_range_over_which_a_ray_is_inside_a_box() {
+        let b = BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let r = Ray::new(Point3::new(0.5, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let miss = Ray::new(Point3::new(2.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(b.intersection_range(&r), Some((4.0, 6.0)));
+        assert_eq!(b.intersection_range(&miss), None);
+    }
+
+    #[test]
+    fn intersecting_a_ray_with_a_non_cubic_bounding_box() {
+        let b = BoundingBox::new(Point3::new(5.0, -2.0, 0.0), Point3::new(11.0, 4.0, 7.0));
+        let cases = [
+            (Point3::new(15.0, 1.0, 2.0), Vector3::new(-1.0, 0.0, 0.0), true),
+            (Point3::new(-5.0, -1.0, 4.0), Vector3::new(1.0, 0.0, 0.0), true),
+            (Point3::new(7.0, 6.0, 5.0), Vector3::new(0.0, -1.0, 0.0), true),
+            (Point3::new(9.0, -5.0, 6.0), Vector3::new(0.0, 1.0, 0.0), true),
+            (Point3::new(8.0, 2.0, 12.0), Vector3::new(0.0, 0.0, -1.0), true),
+            (Point3::new(6.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0), true),
+            (Point3::new(8.0, 1.0, 3.5), Vector3::new(0.0, 0.0, 1.0), true),
+            (Point3::new(9.0, -1.0, -8.0), Vector3::new(2.0, 4.0, 6.0), false),
+            (Point3::new(8.0, 3.0, -4.0), Vector3::new(6.0, 2.0, 4.0), false),
+            (Point3::new(9.0, -1.0, -2.0), Vector3::new(4.0, 6.0, 2.0), false),
+            (Point3::new(4.0, 0.0, 9.0), Vector3::new(0.0, 0.0, -1.0), false),
+            (Point3::new(8.0, 6.0, -1.0), Vector3::new(0.0, -1.0, 0.0), false),
+            (Point3::new(12.0, 5.0, 4.0), Vector3::new(-1.0, 0.0, 0.0), false)
+        ];
+
+        for (origin, direction, expected) in cases.iter() {
+            let r = Ray::new(*origin, direction.normalize());
+            assert_eq!(b.intersects(&r), *expected);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use crate::bounding_box::*;
+use crate::ray::*;
+
+const MAX_LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone)]
+enum BvhNode {
+    Leaf {
+        bounds: BoundingBox,
+        start: usize,
+        count: usize
+    },
+    Interior {
+        bounds: BoundingBox,
+        left: usize,
+        right: usize
+    }
+}
+
+impl BvhNode {
+    fn bounds(&self) -> &BoundingBox {
+        match self {
+            BvhNode::Leaf { bounds, .. } => bounds,
+            BvhNode::Interior { bounds, .. } => bounds
+        }
+    }
+}
+
+// A bounding volume hierarchy over a list of item bounds. It only answers
+// "which items might this ray hit", returning indices into the original list,
+// so the caller decides how to intersect whatever those items are.
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    item_bounds: Vec<BoundingBox>,
+    nodes: Vec<BvhNode>,
+    ordered: Vec<usize>,
+    unbounded: Vec<usize>
+}
+
+struct Split {
+    axis: usize,
+    position: usize,
+    cost: f64
+}
+
+fn axis_value(bounds: &BoundingBox, axis: usize) -> f64 {
+    let centroid = bounds.centroid();
+    match axis {
+        0 => centroid.0,
+        1 => centroid.1,
+        _ => centroid.2
+    }
+}
+
+fn enclose(item_bounds: &[BoundingBox], items: &[usize]) -> BoundingBox {
+    items.iter().fold(BoundingBox::empty(), |acc, &item| acc.union(&item_bounds[item]))
+}
+
+impl Bvh {
+    pub fn build(item_bounds: &[BoundingBox]) -> Bvh {
+        // Infinite boxes (planes and the like) can't be partitioned by area,
+        // so they are kept aside and offered to every ray. Empty boxes can
+        // never be hit and are left out altogether.
+        let (mut bounded, unbounded): (Vec<usize>, Vec<usize>) = (0..item_bounds.len())
+            .filter(|&item| !item_bounds[item].is_empty())
+            .partition(|&item| item_bounds[item].is_finite());
+        let mut bvh = Bvh {
+            item_bounds: item_bounds.to_vec(),
+            nodes: vec![],
+            ordered: vec![],
+            unbounded
+        };
+        if !bounded.is_empty() {
+            bvh.build_node(&mut bounded);
+        }
+        bvh
+    }
+
+    pub fn len(&self) -> usize {
+        self.item_bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.item_bounds.is_empty()
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        if !self.unbounded.is_empty() {
+            return BoundingBox::infinite();
+        }
+        match self.nodes.first() {
+            Some(root) => *root.bounds(),
+            None => BoundingBox::empty()
+        }
+    }
+
+    fn best_split(&self, items: &mut [usize]) -> Option<Split> {
+        let mut best: Option<Split> = None;
+        for axis in 0..3 {
+            items.sort_by(|&a, &b| {
+                axis_value(&self.item_bounds[a], axis)
+                    .partial_cmp(&axis_value(&self.item_bounds[b], axis))
+                    .unwrap()
+            });
+            let mut right_areas = vec![0.0; items.len()];
+            let mut right = BoundingBox::empty();
+            for i in (1..items.len()).rev() {
+                right = right.union(&self.item_bounds[items[i]]);
+                right_areas[i] = right.surface_area();
+            }
+            let mut left = BoundingBox::empty();
+            for i in 1..items.len() {
+                left = left.union(&self.item_bounds[items[i - 1]]);
+                let cost = i as f64 * left.surface_area() +
+                    (items.len() - i) as f64 * right_areas[i];
+                let improves = match &best {
+                    Some(split) => cost < split.cost,
+                    None => true
+                };
+                if improves {
+                    best = Some(Split {
+                        axis,
+                        position: i,
+                        cost
+                    });
+                }
+            }
+        }
+        best
+    }
+
+    fn build_node(&mut self, items: &mut [usize]) -> usize {
+        let bounds = enclose(&self.item_bounds, items);
+        let index = self.nodes.len();
+        let leaf = BvhNode::Leaf {
+            bounds,
+            start: self.ordered.len(),
+            count: items.len()
+        };
+
+        if items.len() <= MAX_LEAF_SIZE {
+            self.ordered.extend_from_slice(items);
+            self.nodes.push(leaf);
+            return index;
+        }
+
+        let split = self.best_split(items).unwrap();
+        let leaf_cost = items.len() as f64 * bounds.surface_area();
+        if split.cost >= leaf_cost {
+            self.ordered.extend_from_slice(items);
+            self.nodes.push(leaf);
+            return index;
+        }
+
+        items.sort_by(|&a, &b| {
+            axis_value(&self.item_bounds[a], split.axis)
+                .partial_cmp(&axis_value(&self.item_bounds[b], split.axis))
+                .unwrap()
+        });
+        self.nodes.push(leaf);
+        let (left_items, right_items) = items.split_at_mut(split.position);
+        let left = self.build_node(left_items);
+        let right = self.build_node(right_items);
+        self.nodes[index] = BvhNode::Interior {
+            bounds,
+            left,
+            right
+        };
+        index
+    }
+
+    // Indices of every item whose bounds the ray passes through, plus all
+    // unbounded items.
+    pub fn candidates(&self, ray: &Ray) -> Vec<usize> {
+        let mut found = self.unbounded.clone();
+        if self.nodes.is_empty() {
+            return found;
+        }
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            match &self.nodes[node] {
+                BvhNode::Leaf { bounds, start, count } => {
+                    if !bounds.intersects(ray) { continue }
+                    for &item in &self.ordered[*start..*start + *count] {
+                        if self.item_bounds[item].intersects(ray) {
+                            found.push(item);
+                        }
+                    }
+                },
+                BvhNode::Interior { bounds, left, right } => {
+                    if !bounds.intersects(ray) { continue }
+                    stack.push(*right);
+                    stack.push(*left);
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point3::Point3;
+    use crate::vector3::Vector3;
+
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> f64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        fn range(&mut self, min: f64, max: f64) -> f64 {
+            min + (max - min) * self.next()
+        }
+    }
+
+    fn random_boxes(rng: &mut Lcg, count: usize) -> Vec<BoundingBox> {
+        (0..count).map(|_| {
+            let min = Point3::new(rng.range(-50.0, 50.0), rng.range(-50.0, 50.0), rng.range(-50.0, 50.0));
+            let size = Vector3::new(rng.range(0.1, 3.0), rng.range(0.1, 3.0), rng.range(0.1, 3.0));
+            BoundingBox::new(min, min + size)
+        }).collect()
+    }
+
+    fn brute_force(boxes: &[BoundingBox], ray: &Ray) -> Vec<usize> {
+        (0..boxes.len()).filter(|&i| boxes[i].intersects(ray)).collect()
+    }
+
+    #[test]
+    fn an_empty_bvh_has_no_candidates() {
+        let bvh = Bvh::build(&[]);
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(bvh.is_empty());
+        assert!(bvh.candidates(&r).is_empty());
+    }
+
+    #[test]
+    fn bvh_bounds_enclose_all_items() {
+        let boxes = vec![
+            BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0)),
+            BoundingBox::new(Point3::new(4.0, 0.0, 0.0), Point3::new(5.0, 6.0, 1.0))
+        ];
+        let bvh = Bvh::build(&boxes);
+
+        assert_eq!(bvh.len(), 2);
+        assert_eq!(bvh.bounds().min, Point3::new(-1.0, -1.0, -1.0));
+        assert_eq!(bvh.bounds().max, Point3::new(5.0, 6.0, 1.0));
+    }
+
+    #[test]
+    fn unbounded_items_are_always_candidates() {
+        let boxes = vec![
+            BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0)),
+            BoundingBox::infinite()
+        ];
+        let bvh = Bvh::build(&boxes);
+        let r = Ray::new(Point3::new(10.0, 10.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(bvh.candidates(&r), vec![1]);
+        assert!(!bvh.bounds().is_finite());
+    }
+
+    #[test]
+    fn empty_items_are_never_candidates() {
+        let boxes = vec![
+            BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0)),
+            BoundingBox::empty()
+        ];
+        let bvh = Bvh::build(&boxes);
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(bvh.candidates(&r), vec![0]);
+        assert!(bvh.bounds().is_finite());
+    }
+
+    #[test]
+    fn bvh_matches_brute_force() {
+        let mut rng = Lcg(7);
+        let boxes = random_boxes(&mut rng, 2000);
+        let bvh = Bvh::build(&boxes);
+
+        for _ in 0..500 {
+            let origin = Point3::new(rng.range(-80.0, 80.0), rng.range(-80.0, 80.0), rng.range(-80.0, 80.0));
+            let target = Point3::new(rng.range(-50.0, 50.0), rng.range(-50.0, 50.0), rng.range(-50.0, 50.0));
+            let r = Ray::new(origin, (target - origin).normalize());
+            let mut found = bvh.candidates(&r);
+            found.sort_unstable();
+
+            assert_eq!(found, brute_force(&boxes, &r));
+        }
+    }
+
+    #[test]
+    fn bvh_visits_far_fewer_nodes_than_items() {
+        let mut boxes = vec![];
+        for x in 0..40 {
+            for y in 0..40 {
+                let min = Point3::new(x as f64 * 2.0, y as f64 * 2.0, 0.0);
+                boxes.push(BoundingBox::new(min, min + Vector3::new(1.0, 1.0, 1.0)));
+            }
+        }
+        let bvh = Bvh::build(&boxes);
+        let r = Ray::new(Point3::new(10.5, 20.5, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let mut visited = 0;
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            visited += 1;
+            if let BvhNode::Interior { bounds, left, right } = &bvh.nodes[node] {
+                if bounds.intersects(&r) {
+                    stack.push(*left);
+                    stack.push(*right);
+                }
+            }
+        }
+
+        assert_eq!(bvh.candidates(&r).len(), 1);
+        assert!(visited < boxes.len() / 20);
+    }
+}
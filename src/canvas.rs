@@ -1,10 +1,97 @@
-use std::process;
-use crate::color::Color;
+pub mod ppm;
+pub mod pfm;
+pub mod png;
+pub mod hdr;
+pub mod composite;
 
+use std::fmt;
+use crate::color::{Color, TransferFunction};
+use crate::color::tone_map::ToneMap;
+use ppm::{PpmFormat, BitDepth};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CanvasError {
+    OutOfBounds {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize
+    },
+    InvalidAlpha {
+        alpha: f64
+    }
+}
+
+impl fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CanvasError::OutOfBounds { x, y, width, height } => write!(
+                f,
+                "Coordinates ({}, {}) outside of {}x{} canvas",
+                x, y, width, height
+            ),
+            CanvasError::InvalidAlpha { alpha } => write!(
+                f,
+                "Alpha {} outside of 0.0 to 1.0",
+                alpha
+            )
+        }
+    }
+}
+
+impl std::error::Error for CanvasError {}
+
+// How the integer writers turn linear canvas values into samples: the tone
+// map brings them into 0.0..=1.0, then the transfer function encodes them.
+// The default clamps and encodes as sRGB.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OutputEncoding {
+    pub tone_map: ToneMap,
+    pub transfer: TransferFunction
+}
+
+impl OutputEncoding {
+    pub fn new(tone_map: ToneMap, transfer: TransferFunction) -> OutputEncoding {
+        OutputEncoding {
+            tone_map,
+            transfer
+        }
+    }
+
+    // Clamped but not gamma encoded, for data rather than pictures.
+    pub fn linear() -> OutputEncoding {
+        OutputEncoding::new(ToneMap::default(), TransferFunction::Linear)
+    }
+
+    pub fn encode(&self, color: Color) -> Color {
+        let mapped = self.tone_map.apply(color);
+        Color::new(
+            self.transfer.encode(mapped.0),
+            self.transfer.encode(mapped.1),
+            self.transfer.encode(mapped.2)
+        )
+    }
+
+    // Luminance is taken after tone mapping, in linear light.
+    pub fn encode_grey(&self, color: Color) -> f64 {
+        self.transfer.encode(self.tone_map.apply(color).luminance())
+    }
+}
+
+impl Default for OutputEncoding {
+    fn default() -> OutputEncoding {
+        OutputEncoding::new(ToneMap::default(), TransferFunction::Srgb)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Canvas {
     width: usize,
     height: usize,
-    canvas: Vec<Color>
+    canvas: Vec<Color>,
+    // Coverage per pixel, 0.0 (transparent) to 1.0 (opaque). Colors are
+    // stored straight, not premultiplied by it.
+    alpha: Vec<f64>
 }
 
 impl Canvas {
@@ -12,61 +99,115 @@ impl Canvas {
         Self {
             width,
             height,
-            canvas: vec![Color(0.0, 0.0, 0.0); width * height]
+            canvas: vec![Color::BLACK; width * height],
+            alpha: vec![1.0; width * height]
+        }
+    }
+
+    // A fully transparent canvas, for overlays.
+    pub fn transparent(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            canvas: vec![Color::BLACK; width * height],
+            alpha: vec![0.0; width * height]
         }
     }
 
-    fn coords_to_index(&self, x: usize, y: usize) -> Result<usize, String> {
-        let index: usize = y * self.width + x;
-        if index < self.canvas.len() {
-            Ok(index)
+    // Wraps pixels decoded from a file; the reader has already checked there
+    // are width * height of them.
+    pub(crate) fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        let alpha = vec![1.0; pixels.len()];
+        Self {
+            width,
+            height,
+            canvas: pixels,
+            alpha
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn coords_to_index(&self, x: usize, y: usize) -> Result<usize, CanvasError> {
+        // Checking x on its own matters: an x past the right edge would
+        // otherwise wrap into the start of the next row.
+        if x < self.width && y < self.height {
+            Ok(y * self.width + x)
         } else {
-            Err(format!("Coordinates outside of canvas {} {} {}", x, y, index))
+            Err(CanvasError::OutOfBounds {
+                x,
+                y,
+                width: self.width,
+                height: self.height
+            })
         }
     }
 
-    pub fn pixel_at(&self, x: usize, y: usize) -> Color {
-        let index = self.coords_to_index(x, y).unwrap_or_else(|err| {
-            println!("{}", err);
-            process::exit(1);
-        });
+    pub fn try_pixel_at(&self, x: usize, y: usize) -> Result<Color, CanvasError> {
+        let index = self.coords_to_index(x, y)?;
+        Ok(self.canvas[index])
+    }
+
+    pub fn try_write_pixel(&mut self, x: usize, y: usize, color: Color) -> Result<(), CanvasError> {
+        let index = self.coords_to_index(x, y)?;
+        self.canvas[index] = color;
+        Ok(())
+    }
+
+    pub fn try_alpha_at(&self, x: usize, y: usize) -> Result<f64, CanvasError> {
+        let index = self.coords_to_index(x, y)?;
+        Ok(self.alpha[index])
+    }
+
+    // Alpha is coverage, so anything outside 0.0..=1.0 (or NaN) is rejected.
+    pub fn try_write_alpha(&mut self, x: usize, y: usize, alpha: f64) -> Result<(), CanvasError> {
+        let index = self.coords_to_index(x, y)?;
+        if !(0.0..=1.0).contains(&alpha) {
+            return Err(CanvasError::InvalidAlpha { alpha });
+        }
+        self.alpha[index] = alpha;
+        Ok(())
+    }
+
+    pub fn alpha_at(&self, x: usize, y: usize) -> f64 {
+        self.try_alpha_at(x, y).unwrap_or_else(|err| panic!("{}", err))
+    }
 
-        return self.canvas[index];
+    pub fn write_alpha(&mut self, x: usize, y: usize, alpha: f64) {
+        self.try_write_alpha(x, y, alpha).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn pixel_at(&self, x: usize, y: usize) -> Color {
+        self.try_pixel_at(x, y).unwrap_or_else(|err| panic!("{}", err))
     }
 
     pub fn write_pixel(&mut self, x: usize, y: usize, color: Color) {
-        let index = self.coords_to_index(x, y).unwrap_or_else(|err| {
-            println!("{}", err);
-            process::exit(1);
-        });
+        self.try_write_pixel(x, y, color).unwrap_or_else(|err| panic!("{}", err))
+    }
 
-        self.canvas[index] = color;
+    // A copy brought into 0.0..=1.0 by the tone map, ready for any of the
+    // integer writers.
+    pub fn tone_mapped(&self, tone_map: &ToneMap) -> Canvas {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            canvas: self.canvas.iter().map(|pixel| tone_map.apply(*pixel)).collect(),
+            alpha: self.alpha.clone()
+        }
     }
 
+    // Plain 8-bit sRGB; write_ppm takes other formats and encodings.
     pub fn canvas_to_ppm(&self) -> String {
-        let mut pixel_data = String::from("");
-        let mut chars_on_line: usize = 0;
-        for (i, pixel) in self.canvas.iter().enumerate() {
-            let pixel_integers = pixel.integer_color_data();
-            for channel in pixel_integers.iter() {
-                let channel_string = channel.to_string();
-                let channel_length = channel_string.len();
-                let mut start_char = String::from(" ");
-                if chars_on_line + channel_length > 70 {
-                    start_char = String::from("\n");
-                    chars_on_line = 0;
-                } else if chars_on_line == 0 {
-                    start_char = String::from("");
-                }
-                pixel_data = format!("{}{}{}", pixel_data, start_char, channel_string);
-                chars_on_line += channel_length + 1;
-            }
-            if (i + 1) % self.width == 0 {
-                pixel_data = format!("{}{}", pixel_data, "\n");
-                chars_on_line = 0;
-            }
-        }
-        format!("P3\n{} {}\n255\n{}", self.width, self.height, pixel_data)
+        let mut ppm = vec![];
+        self.write_ppm(&mut ppm, PpmFormat::PlainRgb, BitDepth::Eight, OutputEncoding::default())
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(ppm).unwrap()
     }
 }
 
@@ -92,6 +233,82 @@ mod tests {
         assert_eq!(c.pixel_at(2, 3), red);
     }
 
+    #[test]
+    fn reading_and_writing_pixels_fallibly() {
+        let mut c = Canvas::new(10, 20);
+        let green = Color::new(0.0, 1.0, 0.0);
+
+        assert_eq!(c.try_write_pixel(9, 19, green), Ok(()));
+        assert_eq!(c.try_pixel_at(9, 19), Ok(green));
+    }
+
+    #[test]
+    fn out_of_bounds_pixels_are_errors() {
+        let mut c = Canvas::new(10, 20);
+        let expected = CanvasError::OutOfBounds { x: 3, y: 20, width: 10, height: 20 };
+
+        assert_eq!(c.try_pixel_at(3, 20), Err(expected));
+        assert_eq!(c.try_write_pixel(3, 20, Color::new(1.0, 0.0, 0.0)), Err(expected));
+        assert_eq!(expected.to_string(), "Coordinates (3, 20) outside of 10x20 canvas");
+    }
+
+    #[test]
+    fn x_past_the_right_edge_does_not_wrap_to_the_next_row() {
+        let mut c = Canvas::new(10, 20);
+
+        assert!(c.try_write_pixel(10, 0, Color::new(1.0, 0.0, 0.0)).is_err());
+        assert!(c.try_pixel_at(12, 3).is_err());
+        assert_eq!(c.pixel_at(0, 1), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Coordinates (10, 0) outside of 10x20 canvas")]
+    fn writing_outside_the_canvas_panics() {
+        let mut c = Canvas::new(10, 20);
+        c.write_pixel(10, 0, Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn canvases_start_opaque_or_transparent() {
+        let mut c = Canvas::new(3, 2);
+        let overlay = Canvas::transparent(3, 2);
+
+        assert_eq!(c.alpha_at(2, 1), 1.0);
+        assert_eq!(overlay.alpha_at(2, 1), 0.0);
+        c.write_alpha(2, 1, 0.25);
+        assert_eq!(c.alpha_at(2, 1), 0.25);
+        assert!(c.try_write_alpha(3, 1, 0.5).is_err());
+    }
+
+    #[test]
+    fn alpha_outside_zero_to_one_is_rejected() {
+        let mut c = Canvas::new(1, 1);
+
+        assert_eq!(c.try_write_alpha(0, 0, 1.5), Err(CanvasError::InvalidAlpha { alpha: 1.5 }));
+        assert_eq!(c.try_write_alpha(0, 0, -0.1), Err(CanvasError::InvalidAlpha { alpha: -0.1 }));
+        assert!(c.try_write_alpha(0, 0, f64::NAN).is_err());
+        assert_eq!(c.alpha_at(0, 0), 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Alpha 2 outside of 0.0 to 1.0")]
+    fn writing_an_invalid_alpha_panics() {
+        Canvas::new(1, 1).write_alpha(0, 0, 2.0);
+    }
+
+    #[test]
+    fn tone_mapping_a_canvas_keeps_highlights() {
+        use crate::color::tone_map::ToneMapOperator;
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(3.0, 1.0, 0.0));
+        c.write_pixel(1, 0, Color::new(7.0, 1.0, 0.0));
+        let mapped = c.tone_mapped(&ToneMap::new(ToneMapOperator::Reinhard).unwrap());
+
+        assert_eq!(mapped.pixel_at(0, 0), Color::new(0.75, 0.5, 0.0));
+        assert_eq!(mapped.pixel_at(1, 0), Color::new(0.875, 0.5, 0.0));
+        assert!(mapped.canvas_to_ppm().contains("225 188 0 240 188 0"));
+    }
+
     #[test]
     fn constructing_ppm_header() {
         let c = Canvas::new(5, 3);
@@ -112,7 +329,7 @@ mod tests {
         let ppm_split: Vec<&str> = ppm.split("\n").collect();
         let expected = vec![
             "255 0 0 0 0 0 0 0 0 0 0 0 0 0 0",
-            "0 0 0 0 0 0 0 128 0 0 0 0 0 0 0",
+            "0 0 0 0 0 0 0 188 0 0 0 0 0 0 0",
             "0 0 0 0 0 0 0 0 0 0 0 0 0 0 255"
         ];
 
@@ -130,15 +347,40 @@ mod tests {
         let ppm = c.canvas_to_ppm();
         let ppm_split: Vec<&str> = ppm.split("\n").collect();
         let expected = vec![
-            "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204",
-            "153 255 204 153 255 204 153 255 204 153 255 204 153",
-            "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204",
-            "153 255 204 153 255 204 153 255 204 153 255 204 153"
+            "255 231 203 255 231 203 255 231 203 255 231 203 255 231 203 255 231",
+            "203 255 231 203 255 231 203 255 231 203 255 231 203",
+            "255 231 203 255 231 203 255 231 203 255 231 203 255 231 203 255 231",
+            "203 255 231 203 255 231 203 255 231 203 255 231 203"
         ];
 
         assert_eq!(&ppm_split[3..7], expected);
     }
 
+    #[test]
+    fn linear_ppm_output_is_an_explicit_choice() {
+        let mut c = Canvas::new(1, 1);
+        c.write_pixel(0, 0, Color::new(0.0, 0.5, 0.0));
+        let mut linear = vec![];
+        c.write_ppm(&mut linear, PpmFormat::PlainRgb, BitDepth::Eight, OutputEncoding::linear()).unwrap();
+
+        assert_eq!(c.canvas_to_ppm(), "P3\n1 1\n255\n0 188 0\n");
+        assert_eq!(linear, b"P3\n1 1\n255\n0 128 0\n");
+    }
+
+    #[test]
+    fn writers_can_tone_map_on_the_way_out() {
+        use crate::color::tone_map::ToneMapOperator;
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(3.0, 1.0, 0.0));
+        c.write_pixel(1, 0, Color::new(7.0, 1.0, 0.0));
+        let reinhard = ToneMap::new(ToneMapOperator::Reinhard).unwrap();
+        let mut ppm = vec![];
+        c.write_ppm(&mut ppm, PpmFormat::PlainRgb, BitDepth::Eight, OutputEncoding::new(reinhard, TransferFunction::Srgb)).unwrap();
+
+        assert_eq!(ppm, c.tone_mapped(&reinhard).canvas_to_ppm().as_bytes());
+        assert_eq!(OutputEncoding::default().encode(Color::new(3.0, 0.2158605, -1.0)), Color::new(1.0, 0.5019608, 0.0));
+    }
+
     #[test]
     fn ppm_files_terminate_in_newline() {
         let c = Canvas::new(5, 3);
@@ -0,0 +1,180 @@
+use super::Canvas;
+use crate::color::Color;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CompositeOperation {
+    // Source drawn on top of the destination.
+    Over,
+    // Source kept only where the destination is.
+    In,
+    // Source kept only where the destination is not.
+    Out,
+    // Both summed, alpha capped at 1.0.
+    Add,
+    // Colors multiplied where both overlap, darkening.
+    Multiply,
+    // Inverted colors multiplied where both overlap, lightening.
+    Screen
+}
+
+impl CompositeOperation {
+    // Works on premultiplied colors; returns the premultiplied result and
+    // its alpha.
+    fn combine(&self, source: Color, source_alpha: f64, backdrop: Color, backdrop_alpha: f64) -> (Color, f64) {
+        let over_alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+        match self {
+            CompositeOperation::Over => (source + backdrop * (1.0 - source_alpha), over_alpha),
+            CompositeOperation::In => (source * backdrop_alpha, source_alpha * backdrop_alpha),
+            CompositeOperation::Out => (source * (1.0 - backdrop_alpha), source_alpha * (1.0 - backdrop_alpha)),
+            CompositeOperation::Add => (source + backdrop, (source_alpha + backdrop_alpha).min(1.0)),
+            CompositeOperation::Multiply => (
+                source * backdrop + source * (1.0 - backdrop_alpha) + backdrop * (1.0 - source_alpha),
+                over_alpha
+            ),
+            CompositeOperation::Screen => (source + backdrop - source * backdrop, over_alpha)
+        }
+    }
+}
+
+// Where a source span placed at offset overlaps a destination span: the
+// first source index, the first destination index and how many overlap.
+fn clip(offset: isize, source_length: usize, destination_length: usize) -> (usize, usize, usize) {
+    let (source_start, destination_start) = if offset < 0 {
+        (offset.unsigned_abs(), 0)
+    } else {
+        (0, offset as usize)
+    };
+    let length = source_length.saturating_sub(source_start).min(destination_length.saturating_sub(destination_start));
+    (source_start, destination_start, length)
+}
+
+impl Canvas {
+    // Composites source onto this canvas with its top left corner at
+    // (left, top), which may be negative; parts of source falling outside
+    // the canvas on any side are dropped.
+    pub fn composite(&mut self, source: &Canvas, left: isize, top: isize, operation: CompositeOperation) {
+        let (source_x, x, columns) = clip(left, source.width, self.width);
+        let (source_y, y, rows) = clip(top, source.height, self.height);
+        for row in 0..rows {
+            for column in 0..columns {
+                let from = (source_y + row) * source.width + source_x + column;
+                let to = (y + row) * self.width + x + column;
+                let (source_alpha, backdrop_alpha) = (source.alpha[from], self.alpha[to]);
+                let (color, alpha) = operation.combine(
+                    source.canvas[from] * source_alpha,
+                    source_alpha,
+                    self.canvas[to] * backdrop_alpha,
+                    backdrop_alpha
+                );
+                self.canvas[to] = if alpha > 0.0 { color / alpha } else { Color::BLACK };
+                self.alpha[to] = alpha;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(color: Color, alpha: f64) -> Canvas {
+        let mut c = Canvas::transparent(1, 1);
+        c.write_pixel(0, 0, color);
+        c.write_alpha(0, 0, alpha);
+        c
+    }
+
+    fn composite(backdrop: (Color, f64), source: (Color, f64), operation: CompositeOperation) -> (Color, f64) {
+        let mut c = pixel(backdrop.0, backdrop.1);
+        c.composite(&pixel(source.0, source.1), 0, 0, operation);
+        (c.pixel_at(0, 0), c.alpha_at(0, 0))
+    }
+
+    #[test]
+    fn compositing_over() {
+        let opaque = composite((Color::BLUE, 1.0), (Color::RED, 1.0), CompositeOperation::Over);
+        let half = composite((Color::BLUE, 1.0), (Color::RED, 0.5), CompositeOperation::Over);
+        let both_half = composite((Color::BLUE, 0.5), (Color::RED, 0.5), CompositeOperation::Over);
+
+        assert_eq!(opaque, (Color::RED, 1.0));
+        assert_eq!(half, (Color::new(0.5, 0.0, 0.5), 1.0));
+        assert_eq!(both_half.0, Color::new(2.0 / 3.0, 0.0, 1.0 / 3.0));
+        assert_eq!(both_half.1, 0.75);
+    }
+
+    #[test]
+    fn compositing_in_and_out() {
+        let inside = composite((Color::BLUE, 0.5), (Color::RED, 0.8), CompositeOperation::In);
+        let outside = composite((Color::BLUE, 0.5), (Color::RED, 0.8), CompositeOperation::Out);
+        let nothing_in = composite((Color::BLUE, 0.0), (Color::RED, 1.0), CompositeOperation::In);
+
+        assert_eq!(inside.0, Color::RED);
+        assert!((inside.1 - 0.4).abs() < 1e-12);
+        assert_eq!(outside.0, Color::RED);
+        assert!((outside.1 - 0.4).abs() < 1e-12);
+        assert_eq!(nothing_in, (Color::BLACK, 0.0));
+    }
+
+    #[test]
+    fn adding_keeps_unclamped_color() {
+        let sum = composite((Color::new(0.8, 0.2, 0.0), 1.0), (Color::new(0.6, 0.2, 0.0), 1.0), CompositeOperation::Add);
+
+        assert_eq!(sum, (Color::new(1.4, 0.4, 0.0), 1.0));
+    }
+
+    #[test]
+    fn multiplying_and_screening_opaque_layers() {
+        let backdrop = (Color::new(0.5, 1.0, 0.2), 1.0);
+        let source = (Color::new(0.5, 0.5, 0.0), 1.0);
+
+        assert_eq!(composite(backdrop, source, CompositeOperation::Multiply), (Color::new(0.25, 0.5, 0.0), 1.0));
+        assert_eq!(composite(backdrop, source, CompositeOperation::Screen), (Color::new(0.75, 1.0, 0.2), 1.0));
+    }
+
+    #[test]
+    fn multiplying_over_transparency_shows_the_source() {
+        let result = composite((Color::BLACK, 0.0), (Color::new(0.5, 0.5, 0.0), 1.0), CompositeOperation::Multiply);
+
+        assert_eq!(result, (Color::new(0.5, 0.5, 0.0), 1.0));
+    }
+
+    #[test]
+    fn compositing_a_watermark_at_an_offset() {
+        let mut render = Canvas::new(4, 3);
+        let mut mark = Canvas::transparent(2, 2);
+        mark.write_pixel(0, 0, Color::WHITE);
+        mark.write_alpha(0, 0, 0.5);
+        mark.write_pixel(1, 1, Color::WHITE);
+        mark.write_alpha(1, 1, 1.0);
+        render.composite(&mark, 3, 1, CompositeOperation::Over);
+
+        assert_eq!(render.pixel_at(3, 1), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(render.pixel_at(3, 2), Color::BLACK);
+        assert_eq!(render.alpha_at(3, 2), 1.0);
+        assert_eq!(render.pixel_at(0, 0), Color::BLACK);
+    }
+
+    #[test]
+    fn compositing_past_the_top_left_corner() {
+        let mut render = Canvas::new(3, 3);
+        let mut mark = Canvas::new(2, 2);
+        mark.write_pixel(1, 1, Color::WHITE);
+        render.composite(&mark, -1, -1, CompositeOperation::Over);
+
+        assert_eq!(render.pixel_at(0, 0), Color::WHITE);
+        assert_eq!(render.pixel_at(1, 0), Color::BLACK);
+        assert_eq!(render.pixel_at(1, 1), Color::BLACK);
+    }
+
+    #[test]
+    fn compositing_entirely_off_the_canvas_changes_nothing() {
+        let mut render = Canvas::new(2, 2);
+        let mut mark = Canvas::new(2, 2);
+        mark.write_pixel(0, 0, Color::WHITE);
+        for &(left, top) in [(-2, 0), (0, -5), (2, 0), (isize::MIN, isize::MAX)].iter() {
+            render.composite(&mark, left, top, CompositeOperation::Over);
+        }
+
+        assert_eq!(render.canvas_to_ppm(), Canvas::new(2, 2).canvas_to_ppm());
+    }
+}
@@ -0,0 +1,238 @@
+use std::io::{self, Write};
+use super::Canvas;
+use super::ppm::{PpmError, sample_count};
+use crate::color::Color;
+
+// Shared-exponent encoding: the brightest channel sets an exponent and all
+// three mantissas are stored as bytes relative to it. Negative and NaN values
+// clamp to zero; anything beyond the largest encodable value (about 2^127,
+// including infinity) saturates to it.
+fn to_rgbe(color: &Color) -> [u8; 4] {
+    let channel = |value: f64| if value > 0.0 { value.min(f64::MAX) } else { 0.0 };
+    let (red, green, blue) = (channel(color.0), channel(color.1), channel(color.2));
+    let brightest = red.max(green).max(blue);
+    if brightest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let mut exponent = brightest.log2().floor() as i32 + 1;
+    if brightest / 2f64.powi(exponent) >= 1.0 {
+        exponent += 1;
+    }
+    let exponent = exponent.min(127);
+    let scale = 256.0 / 2f64.powi(exponent);
+    let mantissa = |value: f64| (value * scale).min(255.0) as u8;
+    [mantissa(red), mantissa(green), mantissa(blue), (exponent + 128) as u8]
+}
+
+fn from_rgbe(rgbe: &[u8]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let scale = 2f64.powi(rgbe[3] as i32 - 136);
+    Color::new(
+        (rgbe[0] as f64 + 0.5) * scale,
+        (rgbe[1] as f64 + 0.5) * scale,
+        (rgbe[2] as f64 + 0.5) * scale
+    )
+}
+
+fn split_header(data: &[u8]) -> Result<(&str, &[u8]), PpmError> {
+    // The header ends at a blank line; the resolution string follows it.
+    let blank = data.windows(2).position(|pair| pair == b"\n\n")
+        .ok_or_else(|| PpmError::new(String::from("header has no terminating blank line")))?;
+    let resolution_end = data[blank + 2..].iter().position(|byte| *byte == b'\n')
+        .ok_or_else(|| PpmError::new(String::from("missing resolution line")))? + blank + 2;
+    let header = std::str::from_utf8(&data[..resolution_end])
+        .map_err(|_| PpmError::new(String::from("header contains invalid UTF-8")))?;
+    Ok((header, &data[resolution_end + 1..]))
+}
+
+// Reads one scanline, either flat RGBE quadruples or the run-length encoded
+// form that stores each component separately.
+fn read_scanline(data: &[u8], position: &mut usize, width: usize) -> Result<Vec<u8>, PpmError> {
+    let truncated = || PpmError::new(String::from("unexpected end of pixel data"));
+    let start = *position;
+    let is_rle = (8..=0x7fff).contains(&width)
+        && data.len() >= start + 4
+        && data[start] == 2 && data[start + 1] == 2
+        && ((data[start + 2] as usize) << 8 | data[start + 3] as usize) == width;
+    if !is_rle {
+        let end = width.checked_mul(4).and_then(|length| length.checked_add(start)).ok_or_else(truncated)?;
+        let bytes = data.get(start..end).ok_or_else(truncated)?;
+        *position = end;
+        return Ok(bytes.to_vec());
+    }
+
+    *position += 4;
+    let mut scanline = vec![0; width * 4];
+    for component in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *data.get(*position).ok_or_else(truncated)? as usize;
+            *position += 1;
+            let (length, is_run) = if count > 128 { (count - 128, true) } else { (count, false) };
+            if length == 0 || x + length > width {
+                return Err(PpmError::new(String::from("run overflows scanline")));
+            }
+            for i in 0..length {
+                let value = *data.get(*position).ok_or_else(truncated)?;
+                if !is_run {
+                    *position += 1;
+                }
+                scanline[(x + i) * 4 + component] = value;
+            }
+            if is_run {
+                *position += 1;
+            }
+            x += length;
+        }
+    }
+    Ok(scanline)
+}
+
+// Reads Radiance RGBE (.hdr) images with the standard -Y height +X width
+// orientation. EXPOSURE lines are undone so values come back as rendered.
+pub fn parse_hdr(data: &[u8]) -> Result<Canvas, PpmError> {
+    let (header, body) = split_header(data)?;
+    let mut lines = header.lines();
+    match lines.next() {
+        Some(line) if line.starts_with("#?") => (),
+        _ => return Err(PpmError::new(String::from("missing '#?' magic line")))
+    }
+
+    let mut exposure = 1.0;
+    let mut resolution = "";
+    for line in lines {
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(PpmError::new(format!("unsupported format '{}'", format)));
+            }
+        } else if let Some(value) = line.strip_prefix("EXPOSURE=") {
+            let invalid = || PpmError::new(format!("invalid exposure '{}'", value));
+            let factor = value.trim().parse::<f64>().map_err(|_| invalid())?;
+            if factor <= 0.0 || !factor.is_finite() {
+                return Err(invalid());
+            }
+            exposure *= factor;
+        } else if !line.is_empty() {
+            resolution = line;
+        }
+    }
+
+    let tokens: Vec<&str> = resolution.split_whitespace().collect();
+    if tokens.len() != 4 || tokens[0] != "-Y" || tokens[2] != "+X" {
+        return Err(PpmError::new(format!("unsupported resolution line '{}'", resolution)));
+    }
+    let dimension = |token: &str| token.parse::<usize>()
+        .map_err(|_| PpmError::new(format!("invalid dimension '{}'", token)));
+    let height = dimension(tokens[1])?;
+    let width = dimension(tokens[3])?;
+
+    // Pixels are only kept as scanlines decode, so a resolution line
+    // promising more than the data holds fails without a huge allocation.
+    sample_count(width, height, 4)?;
+    if width == 0 {
+        return Ok(Canvas::from_pixels(width, height, vec![]));
+    }
+    let mut pixels = vec![];
+    let mut position = 0;
+    for _ in 0..height {
+        let scanline = read_scanline(body, &mut position, width)?;
+        pixels.extend(scanline.chunks(4).map(|rgbe| from_rgbe(rgbe) * (1.0 / exposure)));
+    }
+    Ok(Canvas::from_pixels(width, height, pixels))
+}
+
+impl Canvas {
+    // Writes an uncompressed Radiance RGBE image.
+    pub fn write_hdr<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", self.height, self.width)?;
+        for row in self.canvas.chunks(self.width.max(1)) {
+            let bytes: Vec<u8> = row.iter().flat_map(|pixel| to_rgbe(pixel).to_vec()).collect();
+            writer.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Color, b: Color) -> bool {
+        let tolerance = |x: f64, y: f64| (x - y).abs() <= y.abs().max(b.0).max(b.1).max(b.2) / 128.0;
+        tolerance(a.0, b.0) && tolerance(a.1, b.1) && tolerance(a.2, b.2)
+    }
+
+    #[test]
+    fn encoding_rgbe() {
+        assert_eq!(to_rgbe(&Color::new(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(to_rgbe(&Color::new(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(&Color::new(-1.0, 3.0, 0.0)), [0, 192, 0, 130]);
+    }
+
+    #[test]
+    fn encoding_out_of_range_values_saturates() {
+        assert_eq!(to_rgbe(&Color::new(f64::INFINITY, 1.0, 0.0)), [255, 0, 0, 255]);
+        assert_eq!(to_rgbe(&Color::new(1e300, 0.0, 0.0)), [255, 0, 0, 255]);
+        assert_eq!(to_rgbe(&Color::new(f64::NAN, 0.5, f64::NEG_INFINITY)), [0, 128, 0, 128]);
+        assert_eq!(to_rgbe(&Color::new(f64::NAN, f64::NAN, f64::NAN)), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn hdr_output_round_trips_bright_values() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color::new(12.5, 0.0, 0.75));
+        c.write_pixel(1, 0, Color::new(1.0, 1.0, 1.0));
+        c.write_pixel(2, 1, Color::new(0.01, 1000.0, 0.001));
+        let mut data = vec![];
+        c.write_hdr(&mut data).unwrap();
+        let read = parse_hdr(&data).unwrap();
+
+        assert_eq!(read.width(), 3);
+        assert_eq!(read.height(), 2);
+        for (a, b) in read.canvas.iter().zip(c.canvas.iter()) {
+            assert!(close(*a, *b), "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn reading_run_length_encoded_scanlines() {
+        let mut data = b"#?RGBE\nEXPOSURE=2.0\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // Red: a run of eight; green: eight literals; blue: two runs; exponent: one run.
+        data.extend_from_slice(&[136, 128]);
+        data.extend_from_slice(&[8, 0, 32, 64, 96, 128, 160, 192, 224]);
+        data.extend_from_slice(&[132, 0, 132, 255]);
+        data.extend_from_slice(&[136, 129]);
+        let c = parse_hdr(&data).unwrap();
+
+        assert!(close(c.pixel_at(0, 0), Color::new(0.5, 0.0, 0.0)));
+        assert!(close(c.pixel_at(3, 0), Color::new(0.5, 0.375, 0.0)));
+        assert!(close(c.pixel_at(7, 0), Color::new(0.5, 0.875, 1.0)));
+    }
+
+    #[test]
+    fn malformed_hdr_headers_are_described() {
+        let error = |data: &[u8]| parse_hdr(data).unwrap_err().to_string();
+
+        assert_eq!(error(b"P6\n\n-Y 1 +X 1\n"), "invalid image: missing '#?' magic line");
+        assert_eq!(error(b"#?RADIANCE\n-Y 1 +X 1\n"), "invalid image: header has no terminating blank line");
+        assert_eq!(error(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n"), "invalid image: unsupported format '32-bit_rle_xyze'");
+        assert_eq!(error(b"#?RADIANCE\n\n+Y 1 +X 1\n"), "invalid image: unsupported resolution line '+Y 1 +X 1'");
+        assert_eq!(error(b"#?RADIANCE\n\n-Y 1 +X 2\n\x80\x80\x80\x81"), "invalid image: unexpected end of pixel data");
+        assert_eq!(error(b"#?RADIANCE\nEXPOSURE=0\n\n-Y 1 +X 1\n\x80\x80\x80\x81"), "invalid image: invalid exposure '0'");
+        assert_eq!(error(b"#?RADIANCE\nEXPOSURE=-2\n\n-Y 1 +X 1\n\x80\x80\x80\x81"), "invalid image: invalid exposure '-2'");
+        assert_eq!(error(b"#?RADIANCE\nEXPOSURE=inf\n\n-Y 1 +X 1\n\x80\x80\x80\x81"), "invalid image: invalid exposure 'inf'");
+    }
+
+    #[test]
+    fn huge_resolutions_fail_before_allocating() {
+        let error = |data: &[u8]| parse_hdr(data).unwrap_err().message;
+        let overflowing = format!("#?RADIANCE\n\n-Y 1 +X {}\n\x01\x01\x01\x01", usize::MAX / 2);
+
+        assert_eq!(error(overflowing.as_bytes()), format!("image size {}x1 is too large", usize::MAX / 2));
+        assert_eq!(error(b"#?RADIANCE\n\n-Y 100000 +X 100000\n\x80\x80\x80\x81"), "unexpected end of pixel data");
+        assert_eq!(parse_hdr(b"#?RADIANCE\n\n-Y 100000 +X 0\n").unwrap().height(), 100000);
+    }
+}
@@ -0,0 +1,136 @@
+use std::convert::TryInto;
+use std::io::{self, Write};
+use super::Canvas;
+use super::ppm::{HeaderReader, PpmError, sample_count};
+use crate::color::Color;
+
+// Reads colour (PF) and greyscale (Pf) portable float maps. Values are kept
+// unclamped; a negative scale marks little-endian data, and rows are stored
+// bottom to top.
+pub fn parse_pfm(data: &[u8]) -> Result<Canvas, PpmError> {
+    let mut header = HeaderReader::new(data);
+    let channels = match header.token("magic number")? {
+        "PF" => 3,
+        "Pf" => 1,
+        other => return Err(PpmError::new(format!("unsupported magic number '{}'", other)))
+    };
+    let width = header.number("width")?;
+    let height = header.number("height")?;
+    let scale_token = header.token("scale")?;
+    let scale: f64 = scale_token.parse()
+        .map_err(|_| PpmError::new(format!("invalid scale '{}'", scale_token)))?;
+    if scale == 0.0 || !scale.is_finite() {
+        return Err(PpmError::new(format!("invalid scale '{}'", scale_token)));
+    }
+
+    let body = header.binary_data()?;
+    let count = sample_count(width, height, channels)?;
+    let length = count.checked_mul(4).filter(|length| *length <= body.len()).ok_or_else(|| {
+        PpmError::new(format!(
+            "expected {} bytes of pixel data, found {}", count.saturating_mul(4), body.len()
+        ))
+    })?;
+    let samples: Vec<f64> = body[..length].chunks(4)
+        .map(|bytes| {
+            let bytes: [u8; 4] = bytes.try_into().unwrap();
+            let value = if scale < 0.0 { f32::from_le_bytes(bytes) } else { f32::from_be_bytes(bytes) };
+            value as f64
+        })
+        .collect();
+
+    // Rows are stored bottom first, so they're reversed into canvas order.
+    let pixels = samples.chunks(width.max(1) * channels).rev()
+        .flat_map(|row| row.chunks(channels))
+        .map(|values| {
+            let value = |channel: usize| values[channel.min(channels - 1)];
+            Color::new(value(0), value(1), value(2))
+        })
+        .collect();
+    Ok(Canvas::from_pixels(width, height, pixels))
+}
+
+impl Canvas {
+    // Writes a little-endian colour PFM, keeping values above 1.0 intact.
+    pub fn write_pfm<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "PF\n{} {}\n-1.0\n", self.width, self.height)?;
+        for row in self.canvas.chunks(self.width.max(1)).rev() {
+            let mut bytes = Vec::with_capacity(row.len() * 12);
+            for pixel in row {
+                for value in [pixel.0, pixel.1, pixel.2].iter() {
+                    bytes.extend_from_slice(&(*value as f32).to_le_bytes());
+                }
+            }
+            writer.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pfm(header: &str, values: &[f32], little_endian: bool) -> Vec<u8> {
+        let mut data = header.as_bytes().to_vec();
+        for value in values {
+            if little_endian {
+                data.extend_from_slice(&value.to_le_bytes());
+            } else {
+                data.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn reading_a_little_endian_colour_pfm() {
+        let data = pfm("PF\n1 2\n-1.0\n", &[0.5, 2.0, 8.0, 1.0, 0.0, 0.25], true);
+        let c = parse_pfm(&data).unwrap();
+
+        // The first stored row is the bottom of the image.
+        assert_eq!(c.pixel_at(0, 1), Color::new(0.5, 2.0, 8.0));
+        assert_eq!(c.pixel_at(0, 0), Color::new(1.0, 0.0, 0.25));
+    }
+
+    #[test]
+    fn reading_a_big_endian_greyscale_pfm() {
+        let data = pfm("Pf\n2 1\n1.0\n", &[3.5, 0.125], false);
+        let c = parse_pfm(&data).unwrap();
+
+        assert_eq!(c.pixel_at(0, 0), Color::new(3.5, 3.5, 3.5));
+        assert_eq!(c.pixel_at(1, 0), Color::new(0.125, 0.125, 0.125));
+    }
+
+    #[test]
+    fn pfm_output_round_trips_unclamped_values() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color::new(12.5, 0.0, 0.75));
+        c.write_pixel(2, 1, Color::new(-1.0, 1000.0, 0.001));
+        let mut data = vec![];
+        c.write_pfm(&mut data).unwrap();
+
+        assert!(data.starts_with(b"PF\n3 2\n-1.0\n"));
+        assert_eq!(data.len(), 12 + 3 * 2 * 12);
+        assert_eq!(parse_pfm(&data).unwrap().canvas, c.canvas);
+    }
+
+    #[test]
+    fn malformed_pfm_headers_are_described() {
+        let error = |data: &[u8]| parse_pfm(data).unwrap_err().to_string();
+
+        assert_eq!(error(b"P6\n1 1\n-1.0\n"), "invalid image: unsupported magic number 'P6'");
+        assert_eq!(error(b"PF\n1 1\nbig\n"), "invalid image: invalid scale 'big'");
+        assert_eq!(error(b"PF\n1 1\n0\n"), "invalid image: invalid scale '0'");
+        assert_eq!(error(b"Pf\n2 1\n-1.0\n\0\0"), "invalid image: expected 8 bytes of pixel data, found 2");
+    }
+
+    #[test]
+    fn huge_declared_sizes_fail_before_allocating() {
+        let error = |data: &[u8]| parse_pfm(data).unwrap_err().message;
+        let overflowing = format!("PF\n{} 3\n-1.0\n\0\0", usize::MAX / 2);
+
+        assert_eq!(error(overflowing.as_bytes()), format!("image size {}x3 is too large", usize::MAX / 2));
+        assert_eq!(error(b"PF\n100000 100000\n-1.0\n\0\0"), "expected 120000000000 bytes of pixel data, found 2");
+        assert_eq!(parse_pfm(b"PF\n0 100000\n-1.0\n").unwrap().height(), 100000);
+    }
+}
@@ -0,0 +1,596 @@
+use std::io::{self, Write};
+use super::{Canvas, OutputEncoding};
+use super::ppm::BitDepth;
+use crate::color::{Color, TransferFunction};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+const WINDOW_SIZE: usize = 32768;
+const WINDOW_MASK: usize = WINDOW_SIZE - 1;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+
+const LENGTH_BASES: [usize; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258
+];
+const LENGTH_EXTRA_BITS: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+    3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0
+];
+const DISTANCE_BASES: [usize; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577
+];
+const DISTANCE_EXTRA_BITS: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+    7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13
+];
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+// Deflate packs values starting from the least significant bit, except for
+// Huffman codes, which go most significant bit first.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u32
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {
+            bytes: vec![],
+            buffer: 0,
+            count: 0
+        }
+    }
+
+    fn bits(&mut self, value: u32, count: u32) {
+        self.buffer |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn code(&mut self, code: u32, length: u32) {
+        let reversed = code.reverse_bits() >> (32 - length);
+        self.bits(reversed, length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+// The fixed Huffman code from RFC 1951 section 3.2.6.
+fn write_symbol(writer: &mut BitWriter, symbol: usize) {
+    let symbol = symbol as u32;
+    match symbol {
+        0..=143 => writer.code(0x30 + symbol, 8),
+        144..=255 => writer.code(0x190 + symbol - 144, 9),
+        256..=279 => writer.code(symbol - 256, 7),
+        _ => writer.code(0xc0 + symbol - 280, 8)
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let code = LENGTH_BASES.iter().rposition(|base| *base <= length).unwrap();
+    write_symbol(writer, 257 + code);
+    writer.bits((length - LENGTH_BASES[code]) as u32, LENGTH_EXTRA_BITS[code]);
+    let code = DISTANCE_BASES.iter().rposition(|base| *base <= distance).unwrap();
+    writer.code(code as u32, 5);
+    writer.bits((distance - DISTANCE_BASES[code]) as u32, DISTANCE_EXTRA_BITS[code]);
+}
+
+fn hash(data: &[u8], position: usize) -> usize {
+    ((data[position] as usize) << 10 ^ (data[position + 1] as usize) << 5 ^ data[position + 2] as usize) & 0x7fff
+}
+
+// Hash chains over the sliding window. previous is a ring indexed by
+// position & WINDOW_MASK: a slot is only reused once its old position has
+// slid out of the window, where the distance check stops the chain anyway.
+struct MatchFinder<'a> {
+    data: &'a [u8],
+    head: Vec<usize>,
+    previous: Vec<usize>
+}
+
+impl<'a> MatchFinder<'a> {
+    fn new(data: &'a [u8]) -> MatchFinder<'a> {
+        MatchFinder {
+            data,
+            head: vec![usize::MAX; 0x8000],
+            previous: vec![usize::MAX; WINDOW_SIZE]
+        }
+    }
+
+    fn insert(&mut self, position: usize) {
+        if position + MIN_MATCH <= self.data.len() {
+            let h = hash(self.data, position);
+            self.previous[position & WINDOW_MASK] = self.head[h];
+            self.head[h] = position;
+        }
+    }
+
+    // Returns the longest (length, distance) match among recent positions
+    // sharing the same hash.
+    fn longest_match(&self, position: usize) -> (usize, usize) {
+        let data = self.data;
+        let mut best = (0, 0);
+        if position + MIN_MATCH > data.len() {
+            return best;
+        }
+        let limit = (data.len() - position).min(MAX_MATCH);
+        let mut candidate = self.head[hash(data, position)];
+        let mut chain = 0;
+        while candidate != usize::MAX && position - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+            let length = (0..limit)
+                .take_while(|i| data[candidate + i] == data[position + i])
+                .count();
+            if length > best.0 {
+                best = (length, position - candidate);
+            }
+            candidate = self.previous[candidate & WINDOW_MASK];
+            chain += 1;
+        }
+        best
+    }
+}
+
+// A single fixed-Huffman block, with LZ77 matches found through hash chains
+// over the last 32K of input.
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    let mut finder = MatchFinder::new(data);
+    let mut position = 0;
+    while position < data.len() {
+        let (length, distance) = finder.longest_match(position);
+        if length >= MIN_MATCH {
+            write_match(&mut writer, length, distance);
+            for offset in 0..length {
+                finder.insert(position + offset);
+            }
+            position += length;
+        } else {
+            write_symbol(&mut writer, data[position] as usize);
+            finder.insert(position);
+            position += 1;
+        }
+    }
+    write_symbol(&mut writer, 256);
+    writer.finish()
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    stream.extend(deflate(data));
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+// Scratch rows for filter_row, kept across scanlines so each row doesn't
+// allocate a buffer per filter type.
+struct FilterBuffers {
+    candidate: Vec<u8>,
+    best: Vec<u8>
+}
+
+impl FilterBuffers {
+    fn new(row_length: usize) -> FilterBuffers {
+        FilterBuffers {
+            candidate: Vec::with_capacity(row_length),
+            best: Vec::with_capacity(row_length)
+        }
+    }
+}
+
+// Tries every filter type on a scanline and keeps the one whose output has the
+// smallest sum of absolute (signed) bytes, the usual heuristic for compressibility.
+fn filter_row(row: &[u8], above: &[u8], bytes_per_pixel: usize, buffers: &mut FilterBuffers, out: &mut Vec<u8>) {
+    let mut best: Option<(u64, u8)> = None;
+    for filter in 0..5u8 {
+        buffers.candidate.clear();
+        buffers.candidate.extend((0..row.len()).map(|i| {
+            let a = if i >= bytes_per_pixel { row[i - bytes_per_pixel] } else { 0 };
+            let b = above[i];
+            let c = if i >= bytes_per_pixel { above[i - bytes_per_pixel] } else { 0 };
+            let predictor = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                _ => paeth(a, b, c)
+            };
+            row[i].wrapping_sub(predictor)
+        }));
+        let cost = buffers.candidate.iter().map(|byte| (*byte as i8).unsigned_abs() as u64).sum();
+        if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+            best = Some((cost, filter));
+            std::mem::swap(&mut buffers.candidate, &mut buffers.best);
+        }
+    }
+    out.push(best.unwrap().1);
+    out.extend_from_slice(&buffers.best);
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut body = kind.to_vec();
+    body.extend_from_slice(data);
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(&body)?;
+    writer.write_all(&crc32(&body).to_be_bytes())
+}
+
+impl Canvas {
+    fn png_samples(&self, depth: BitDepth, alpha: bool, encoding: OutputEncoding) -> Vec<u8> {
+        let max_value = depth.max_value();
+        let mut samples = vec![];
+        for (pixel, coverage) in self.canvas.iter().zip(self.alpha.iter()) {
+            let mut values = encoding.encode(*pixel).scaled_color_data(max_value).to_vec();
+            if alpha {
+                values.push(Color::scale_color_value(*coverage, max_value));
+            }
+            for value in values {
+                match depth {
+                    BitDepth::Eight => samples.push(value as u8),
+                    BitDepth::Sixteen => samples.extend_from_slice(&(value as u16).to_be_bytes())
+                }
+            }
+        }
+        samples
+    }
+
+    // Writes an RGB or RGBA PNG at 8 or 16 bits per channel, taking alpha
+    // from the canvas's alpha plane. The encoding applies to color only; its
+    // transfer function is recorded in an sRGB or gAMA chunk so viewers
+    // decode it.
+    pub fn write_png<W: Write>(&self, writer: &mut W, depth: BitDepth, alpha: bool, encoding: OutputEncoding) -> io::Result<()> {
+        let max_dimension = i32::MAX as usize;
+        if self.width == 0 || self.height == 0 || self.width > max_dimension || self.height > max_dimension {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                "a {}x{} canvas can't be a PNG: both sides must be 1 to 2^31 - 1 pixels",
+                self.width, self.height
+            )));
+        }
+        let channels = if alpha { 4 } else { 3 };
+        let bytes_per_pixel = channels * if depth == BitDepth::Sixteen { 2 } else { 1 };
+        let row_length = self.width * bytes_per_pixel;
+
+        let mut header = vec![];
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.push(if depth == BitDepth::Sixteen { 16 } else { 8 });
+        header.push(if alpha { 6 } else { 2 });
+        header.extend_from_slice(&[0, 0, 0]);
+
+        let samples = self.png_samples(depth, alpha, encoding);
+        let mut filtered = Vec::with_capacity(samples.len() + self.height);
+        let mut buffers = FilterBuffers::new(row_length);
+        let first_above = vec![0; row_length];
+        let mut above: &[u8] = &first_above;
+        for row in samples.chunks(row_length.max(1)).take(self.height) {
+            filter_row(row, above, bytes_per_pixel, &mut buffers, &mut filtered);
+            above = row;
+        }
+
+        writer.write_all(&SIGNATURE)?;
+        write_chunk(writer, b"IHDR", &header)?;
+        match encoding.transfer {
+            TransferFunction::Srgb => write_chunk(writer, b"sRGB", &[0])?,
+            TransferFunction::Linear => write_chunk(writer, b"gAMA", &100_000u32.to_be_bytes())?
+        }
+        write_chunk(writer, b"IDAT", &zlib(&filtered))?;
+        write_chunk(writer, b"IEND", &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    // RFC 1951 section 3.2.5, written out again rather than shared with the
+    // encoder so that a mistake in its tables can't cancel itself out.
+    const RFC_LENGTH_BASES: [usize; 29] = [
+        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+        35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258
+    ];
+    const RFC_LENGTH_EXTRA_BITS: [u32; 29] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+        3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0
+    ];
+    const RFC_DISTANCE_BASES: [usize; 30] = [
+        1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+        257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577
+    ];
+    const RFC_DISTANCE_EXTRA_BITS: [u32; 30] = [
+        0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+        7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13
+    ];
+
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        position: usize
+    }
+
+    impl<'a> BitReader<'a> {
+        fn bit(&mut self) -> u32 {
+            let bit = (self.bytes[self.position / 8] >> (self.position % 8)) & 1;
+            self.position += 1;
+            bit as u32
+        }
+
+        fn bits(&mut self, count: u32) -> u32 {
+            (0..count).fold(0, |value, i| value | self.bit() << i)
+        }
+
+        fn symbol(&mut self) -> usize {
+            let mut code = 0;
+            for length in 1..=9 {
+                code = code << 1 | self.bit();
+                match (length, code) {
+                    (7, 0..=0x17) => return 256 + code as usize,
+                    (8, 0x30..=0xbf) => return (code - 0x30) as usize,
+                    (8, 0xc0..=0xc7) => return 280 + (code - 0xc0) as usize,
+                    (9, _) => return 144 + (code - 0x190) as usize,
+                    _ => ()
+                }
+            }
+            unreachable!()
+        }
+    }
+
+    // Enough of inflate to read stored and fixed-Huffman blocks.
+    fn inflate(bytes: &[u8]) -> Vec<u8> {
+        let mut reader = BitReader { bytes, position: 0 };
+        let mut out: Vec<u8> = vec![];
+        loop {
+            let last = reader.bits(1);
+            match reader.bits(2) {
+                0 => {
+                    reader.position = reader.position.div_ceil(8) * 8;
+                    let start = reader.position / 8;
+                    let length = u16::from_le_bytes([bytes[start], bytes[start + 1]]) as usize;
+                    out.extend_from_slice(&bytes[start + 4..start + 4 + length]);
+                    reader.position = (start + 4 + length) * 8;
+                }
+                1 => loop {
+                    let symbol = reader.symbol();
+                    if symbol < 256 {
+                        out.push(symbol as u8);
+                    } else if symbol == 256 {
+                        break;
+                    } else {
+                        let code = symbol - 257;
+                        let length = RFC_LENGTH_BASES[code] + reader.bits(RFC_LENGTH_EXTRA_BITS[code]) as usize;
+                        let code = (0..5).fold(0, |value, _| value << 1 | reader.bit()) as usize;
+                        let distance = RFC_DISTANCE_BASES[code] + reader.bits(RFC_DISTANCE_EXTRA_BITS[code]) as usize;
+                        assert!(distance <= 32768 && distance <= out.len(), "invalid distance {}", distance);
+                        for _ in 0..length {
+                            out.push(out[out.len() - distance]);
+                        }
+                    }
+                },
+                other => panic!("unsupported block type {}", other)
+            }
+            if last == 1 {
+                return out;
+            }
+        }
+    }
+
+    struct Decoded {
+        header: Vec<u8>,
+        chunks: Vec<Vec<u8>>,
+        samples: Vec<u8>
+    }
+
+    fn decode(png: &[u8]) -> Decoded {
+        assert_eq!(png[..8], SIGNATURE);
+        let mut position = 8;
+        let mut header = vec![];
+        let mut compressed = vec![];
+        let mut ended = false;
+        let mut chunks = vec![];
+        while position < png.len() {
+            let length = u32::from_be_bytes(png[position..position + 4].try_into().unwrap()) as usize;
+            let body = &png[position + 4..position + 8 + length];
+            let crc = u32::from_be_bytes(png[position + 8 + length..position + 12 + length].try_into().unwrap());
+            assert_eq!(crc, crc32(body));
+            chunks.push(body.to_vec());
+            match &body[..4] {
+                b"IHDR" => header = body[4..].to_vec(),
+                b"IDAT" => compressed.extend_from_slice(&body[4..]),
+                b"IEND" => ended = true,
+                _ => ()
+            }
+            position += 12 + length;
+        }
+        assert!(ended);
+
+        assert_eq!((compressed[0] as u16 * 256 + compressed[1] as u16) % 31, 0);
+        let raw = inflate(&compressed[2..]);
+        let checksum = u32::from_be_bytes(compressed[compressed.len() - 4..].try_into().unwrap());
+        assert_eq!(checksum, adler32(&raw));
+
+        let width = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+        let channels = if header[9] == 6 { 4 } else { 3 };
+        let bytes_per_pixel = channels * header[8] as usize / 8;
+        let row_length = width * bytes_per_pixel;
+        let mut samples: Vec<u8> = vec![];
+        let mut above = vec![0u8; row_length];
+        for line in raw.chunks(row_length + 1) {
+            let mut row = vec![0u8; row_length];
+            for i in 0..row_length {
+                let a = if i >= bytes_per_pixel { row[i - bytes_per_pixel] } else { 0 };
+                let c = if i >= bytes_per_pixel { above[i - bytes_per_pixel] } else { 0 };
+                let predictor = match line[0] {
+                    0 => 0,
+                    1 => a,
+                    2 => above[i],
+                    3 => ((a as u16 + above[i] as u16) / 2) as u8,
+                    _ => paeth(a, above[i], c)
+                };
+                row[i] = line[i + 1].wrapping_add(predictor);
+            }
+            samples.extend_from_slice(&row);
+            above = row;
+        }
+        Decoded { header, chunks, samples }
+    }
+
+    fn gradient() -> Canvas {
+        let mut c = Canvas::new(37, 21);
+        for y in 0..21 {
+            for x in 0..37 {
+                c.write_pixel(x, y, Color::new(x as f64 / 36.0, y as f64 / 20.0, 0.5));
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn computing_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn writing_an_eight_bit_png() {
+        let c = gradient();
+        let mut png = vec![];
+        c.write_png(&mut png, BitDepth::Eight, false, OutputEncoding::linear()).unwrap();
+        let decoded = decode(&png);
+
+        assert_eq!(decoded.header, vec![0, 0, 0, 37, 0, 0, 0, 21, 8, 2, 0, 0, 0]);
+        assert_eq!(decoded.samples.len(), 37 * 21 * 3);
+        for (pixel, samples) in c.canvas.iter().zip(decoded.samples.chunks(3)) {
+            let expected: Vec<u8> = pixel.integer_color_data_with(TransferFunction::Linear).iter().map(|v| *v as u8).collect();
+            assert_eq!(samples, &expected[..]);
+        }
+    }
+
+    #[test]
+    fn writing_a_sixteen_bit_png_with_alpha() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(1.0, 0.5, 0.0));
+        let mut png = vec![];
+        c.write_png(&mut png, BitDepth::Sixteen, true, OutputEncoding::linear()).unwrap();
+        let decoded = decode(&png);
+
+        assert_eq!(decoded.header[8..10], [16, 6]);
+        assert_eq!(decoded.samples, vec![
+            0xff, 0xff, 0x80, 0x00, 0x00, 0x00, 0xff, 0xff,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff
+        ]);
+    }
+
+    #[test]
+    fn alpha_comes_from_the_canvas() {
+        let mut c = Canvas::transparent(2, 1);
+        c.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        c.write_alpha(0, 0, 0.5);
+        let mut png = vec![];
+        c.write_png(&mut png, BitDepth::Eight, true, OutputEncoding::default()).unwrap();
+
+        assert_eq!(decode(&png).samples, vec![255, 0, 0, 128, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn srgb_pngs_are_gamma_encoded_and_tagged() {
+        let mut c = Canvas::new(1, 1);
+        c.write_pixel(0, 0, Color::new(0.2158605, 0.0, 1.0));
+        let mut srgb = vec![];
+        c.write_png(&mut srgb, BitDepth::Eight, false, OutputEncoding::default()).unwrap();
+        let mut linear = vec![];
+        c.write_png(&mut linear, BitDepth::Eight, false, OutputEncoding::linear()).unwrap();
+        let srgb = decode(&srgb);
+        let linear = decode(&linear);
+
+        assert_eq!(srgb.samples, vec![128, 0, 255]);
+        assert_eq!(srgb.chunks[1], b"sRGB\0".to_vec());
+        assert_eq!(linear.samples, vec![55, 0, 255]);
+        assert_eq!(linear.chunks[1], [b"gAMA".as_ref(), &[0, 1, 0x86, 0xa0]].concat());
+    }
+
+    #[test]
+    fn repetitive_images_compress() {
+        let c = Canvas::new(200, 200);
+        let mut png = vec![];
+        c.write_png(&mut png, BitDepth::Eight, false, OutputEncoding::linear()).unwrap();
+
+        assert!(png.len() < 1000);
+        assert_eq!(decode(&png).samples, vec![0; 200 * 200 * 3]);
+    }
+
+    #[test]
+    fn deflate_round_trips_arbitrary_data() {
+        let data: Vec<u8> = (0..70000u64).map(|i| (i * i / 7 % 251) as u8).collect();
+
+        assert_eq!(inflate(&deflate(&data)), data);
+        assert_eq!(inflate(&deflate(&[])), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn matches_never_reach_past_the_window() {
+        let block: Vec<u8> = (0..1000u64).map(|i| (i * 7919 % 256) as u8).collect();
+        let filler: Vec<u8> = (0..40000u64).map(|i| (i * i / 3 % 253) as u8).collect();
+        let data = [block.clone(), filler, block].concat();
+
+        assert_eq!(inflate(&deflate(&data)), data);
+        assert_eq!(MatchFinder::new(&data).previous.len(), WINDOW_SIZE);
+    }
+
+    #[test]
+    fn empty_canvases_are_rejected() {
+        for (width, height) in [(0, 5), (5, 0), (0, 0)].iter() {
+            let mut png = vec![];
+            let error = Canvas::new(*width, *height)
+                .write_png(&mut png, BitDepth::Eight, false, OutputEncoding::default())
+                .unwrap_err();
+
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            assert!(png.is_empty());
+        }
+    }
+}
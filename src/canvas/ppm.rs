@@ -0,0 +1,481 @@
+use std::fmt;
+use std::io::{self, Write};
+use super::{Canvas, OutputEncoding};
+use crate::color::{Color, TransferFunction};
+
+const MAX_LINE_LENGTH: usize = 70;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PpmFormat {
+    // P2: greyscale as text
+    PlainGrey,
+    // P3: RGB as text
+    PlainRgb,
+    // P5: greyscale as raw bytes
+    BinaryGrey,
+    // P6: RGB as raw bytes
+    BinaryRgb
+}
+
+impl PpmFormat {
+    fn magic_number(&self) -> &'static str {
+        match self {
+            PpmFormat::PlainGrey => "P2",
+            PpmFormat::PlainRgb => "P3",
+            PpmFormat::BinaryGrey => "P5",
+            PpmFormat::BinaryRgb => "P6"
+        }
+    }
+
+    fn is_binary(&self) -> bool {
+        *self == PpmFormat::BinaryGrey || *self == PpmFormat::BinaryRgb
+    }
+
+    fn is_grey(&self) -> bool {
+        *self == PpmFormat::PlainGrey || *self == PpmFormat::BinaryGrey
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BitDepth {
+    Eight,
+    Sixteen
+}
+
+impl BitDepth {
+    pub fn max_value(&self) -> usize {
+        match self {
+            BitDepth::Eight => 255,
+            BitDepth::Sixteen => 65535
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PpmError {
+    pub message: String
+}
+
+impl PpmError {
+    pub(crate) fn new(message: String) -> PpmError {
+        PpmError {
+            message
+        }
+    }
+}
+
+impl fmt::Display for PpmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid image: {}", self.message)
+    }
+}
+
+impl std::error::Error for PpmError {}
+
+// Reads the whitespace separated header fields shared by the Netpbm family,
+// skipping '#' comments, which may start straight after a field.
+pub(crate) struct HeaderReader<'a> {
+    data: &'a [u8],
+    pub(crate) position: usize
+}
+
+impl<'a> HeaderReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> HeaderReader<'a> {
+        HeaderReader {
+            data,
+            position: 0
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while self.position < self.data.len() {
+            let byte = self.data[self.position];
+            if byte == b'#' {
+                while self.position < self.data.len() && self.data[self.position] != b'\n' {
+                    self.position += 1;
+                }
+            } else if byte.is_ascii_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub(crate) fn token(&mut self, field: &str) -> Result<&'a str, PpmError> {
+        self.skip_whitespace_and_comments();
+        let start = self.position;
+        while self.position < self.data.len()
+            && !self.data[self.position].is_ascii_whitespace()
+            && self.data[self.position] != b'#' {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(PpmError::new(format!("missing {}", field)));
+        }
+        std::str::from_utf8(&self.data[start..self.position])
+            .map_err(|_| PpmError::new(format!("{} is not valid text", field)))
+    }
+
+    pub(crate) fn number(&mut self, field: &str) -> Result<usize, PpmError> {
+        let token = self.token(field)?;
+        token.parse().map_err(|_| PpmError::new(format!("invalid {} '{}'", field, token)))
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    // Binary data starts after exactly one whitespace byte following the header.
+    pub(crate) fn binary_data(&self) -> Result<&'a [u8], PpmError> {
+        match self.data.get(self.position) {
+            Some(byte) if byte.is_ascii_whitespace() => Ok(&self.data[self.position + 1..]),
+            _ => Err(PpmError::new(String::from("header is not followed by whitespace")))
+        }
+    }
+}
+
+// width * height * channels, refusing sizes that don't fit in memory at all.
+pub(crate) fn sample_count(width: usize, height: usize, channels: usize) -> Result<usize, PpmError> {
+    width.checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or_else(|| PpmError::new(format!("image size {}x{} is too large", width, height)))
+}
+
+// Nothing is reserved up front: each sample needs a digit and a separator, so
+// a count the data can't possibly hold is rejected before reading.
+fn read_plain_samples(header: &mut HeaderReader, count: usize) -> Result<Vec<usize>, PpmError> {
+    if count > header.remaining() / 2 {
+        return Err(PpmError::new(format!(
+            "expected {} samples, but only {} bytes of data remain", count, header.remaining()
+        )));
+    }
+    let mut samples = vec![];
+    for _ in 0..count {
+        samples.push(header.number("sample")?);
+    }
+    Ok(samples)
+}
+
+fn read_binary_samples(data: &[u8], count: usize, max_value: usize) -> Result<Vec<usize>, PpmError> {
+    let size = if max_value > 255 { 2 } else { 1 };
+    let length = count.checked_mul(size).filter(|length| *length <= data.len()).ok_or_else(|| {
+        PpmError::new(format!(
+            "expected {} bytes of pixel data, found {}", count.saturating_mul(size), data.len()
+        ))
+    })?;
+    Ok(data[..length].chunks(size)
+        .map(|bytes| bytes.iter().fold(0, |value, byte| (value << 8) | *byte as usize))
+        .collect())
+}
+
+// Reads plain or binary PPM (P3, P6) and PGM (P2, P5) images with any maxval
+// up to 65535, scaling samples to 0.0..=1.0. Grey images fill all three
+// channels. Samples are decoded from the given transfer function, so sRGB
+// textures come back as linear colors.
+pub fn parse_ppm(data: &[u8], transfer: TransferFunction) -> Result<Canvas, PpmError> {
+    let mut header = HeaderReader::new(data);
+    let format = match header.token("magic number")? {
+        "P2" => PpmFormat::PlainGrey,
+        "P3" => PpmFormat::PlainRgb,
+        "P5" => PpmFormat::BinaryGrey,
+        "P6" => PpmFormat::BinaryRgb,
+        other => return Err(PpmError::new(format!("unsupported magic number '{}'", other)))
+    };
+    let width = header.number("width")?;
+    let height = header.number("height")?;
+    let max_value = header.number("maxval")?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(PpmError::new(format!("maxval {} is outside 1..=65535", max_value)));
+    }
+
+    let channels = if format.is_grey() { 1 } else { 3 };
+    let count = sample_count(width, height, channels)?;
+    let samples = if format.is_binary() {
+        read_binary_samples(header.binary_data()?, count, max_value)?
+    } else {
+        read_plain_samples(&mut header, count)?
+    };
+    if let Some(sample) = samples.iter().find(|sample| **sample > max_value) {
+        return Err(PpmError::new(format!("sample {} exceeds maxval {}", sample, max_value)));
+    }
+
+    let scale = max_value as f64;
+    let pixels = samples.chunks(channels)
+        .map(|values| {
+            let value = |channel: usize| transfer.decode(values[channel.min(channels - 1)] as f64 / scale);
+            Color::new(value(0), value(1), value(2))
+        })
+        .collect();
+    Ok(Canvas::from_pixels(width, height, pixels))
+}
+
+// The one (grey) or three (RGB) samples of a pixel; only the first count
+// entries are used.
+fn pixel_samples(pixel: &Color, format: PpmFormat, max_value: usize, encoding: OutputEncoding) -> ([usize; 3], usize) {
+    if format.is_grey() {
+        ([Color::scale_color_value(encoding.encode_grey(*pixel), max_value), 0, 0], 1)
+    } else {
+        (encoding.encode(*pixel).scaled_color_data(max_value), 3)
+    }
+}
+
+// Formats value in decimal at the end of digits and returns that part.
+fn decimal(value: usize, digits: &mut [u8; 20]) -> &[u8] {
+    let mut start = digits.len();
+    let mut rest = value;
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (rest % 10) as u8;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    &digits[start..]
+}
+
+// Plain formats keep lines within 70 characters and start each image row
+// on a new line.
+fn push_plain_row(buffer: &mut Vec<u8>, row: &[Color], format: PpmFormat, max_value: usize, encoding: OutputEncoding) {
+    let mut line_start = buffer.len();
+    let mut digits = [0; 20];
+    for pixel in row {
+        let (samples, count) = pixel_samples(pixel, format, max_value, encoding);
+        for sample in samples[..count].iter() {
+            let text = decimal(*sample, &mut digits);
+            let line_length = buffer.len() - line_start;
+            if line_length > 0 && line_length + 1 + text.len() > MAX_LINE_LENGTH {
+                buffer.push(b'\n');
+                line_start = buffer.len();
+            } else if line_length > 0 {
+                buffer.push(b' ');
+            }
+            buffer.extend_from_slice(text);
+        }
+    }
+    buffer.push(b'\n');
+}
+
+// Binary samples are one byte each, or two big-endian bytes for 16 bit.
+fn push_binary_row(buffer: &mut Vec<u8>, row: &[Color], format: PpmFormat, max_value: usize, encoding: OutputEncoding) {
+    for pixel in row {
+        let (samples, count) = pixel_samples(pixel, format, max_value, encoding);
+        for sample in samples[..count].iter() {
+            if max_value > 255 {
+                buffer.extend_from_slice(&(*sample as u16).to_be_bytes());
+            } else {
+                buffer.push(*sample as u8);
+            }
+        }
+    }
+}
+
+impl Canvas {
+    // Values are tone mapped and encoded before quantising; the default
+    // encoding suits images meant to be viewed.
+    pub fn write_ppm<W: Write>(&self, writer: &mut W, format: PpmFormat, depth: BitDepth, encoding: OutputEncoding) -> io::Result<()> {
+        let max_value = depth.max_value();
+        write!(writer, "{}\n{} {}\n{}\n", format.magic_number(), self.width, self.height, max_value)?;
+        // One buffer reused for every row; plain samples take at most six
+        // characters with their separator.
+        let channels = if format.is_grey() { 1 } else { 3 };
+        let sample_size = if format.is_binary() { 2 } else { 6 };
+        let mut buffer = Vec::with_capacity(self.width * channels * sample_size + 1);
+        for row in self.canvas.chunks(self.width.max(1)) {
+            buffer.clear();
+            if format.is_binary() {
+                push_binary_row(&mut buffer, row, format, max_value, encoding);
+            } else {
+                push_plain_row(&mut buffer, row, format, max_value, encoding);
+            }
+            writer.write_all(&buffer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(canvas: &Canvas, format: PpmFormat, depth: BitDepth) -> Vec<u8> {
+        let mut out = vec![];
+        canvas.write_ppm(&mut out, format, depth, OutputEncoding::linear()).unwrap();
+        out
+    }
+
+    #[test]
+    fn writing_binary_ppm() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(1.0, 0.5, 0.0));
+        c.write_pixel(1, 0, Color::new(0.0, 0.2, 1.5));
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 128, 0, 0, 51, 255]);
+
+        assert_eq!(write(&c, PpmFormat::BinaryRgb, BitDepth::Eight), expected);
+    }
+
+    #[test]
+    fn writing_sixteen_bit_binary_ppm() {
+        let mut c = Canvas::new(1, 1);
+        c.write_pixel(0, 0, Color::new(1.0, 0.5, 0.0));
+        let mut expected = b"P6\n1 1\n65535\n".to_vec();
+        expected.extend_from_slice(&[0xff, 0xff, 0x80, 0x00, 0x00, 0x00]);
+
+        assert_eq!(write(&c, PpmFormat::BinaryRgb, BitDepth::Sixteen), expected);
+    }
+
+    #[test]
+    fn writing_binary_pgm_uses_luminance() {
+        let mut c = Canvas::new(3, 1);
+        c.write_pixel(0, 0, Color::new(1.0, 1.0, 1.0));
+        c.write_pixel(1, 0, Color::new(0.0, 1.0, 0.0));
+        let mut expected = b"P5\n3 1\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 182, 0]);
+
+        assert_eq!(write(&c, PpmFormat::BinaryGrey, BitDepth::Eight), expected);
+    }
+
+    #[test]
+    fn writing_plain_pgm() {
+        let mut c = Canvas::new(2, 2);
+        c.write_pixel(1, 1, Color::new(0.5, 0.5, 0.5));
+        let expected = "P2\n2 2\n65535\n0 0\n0 32768\n";
+
+        assert_eq!(write(&c, PpmFormat::PlainGrey, BitDepth::Sixteen), expected.as_bytes());
+    }
+
+    #[test]
+    fn formatting_decimal_samples() {
+        let mut digits = [0; 20];
+
+        assert_eq!(decimal(0, &mut digits), b"0");
+        assert_eq!(decimal(7, &mut digits), b"7");
+        assert_eq!(decimal(65535, &mut digits), b"65535");
+        assert_eq!(decimal(usize::MAX, &mut digits), usize::MAX.to_string().as_bytes());
+    }
+
+    #[test]
+    fn plain_lines_wrap_at_seventy_characters() {
+        let mut c = Canvas::new(40, 1);
+        for x in 0..40 {
+            c.write_pixel(x, 0, Color::new(1.0, 1.0, 1.0));
+        }
+        let ppm = String::from_utf8(write(&c, PpmFormat::PlainRgb, BitDepth::Sixteen)).unwrap();
+
+        for line in ppm.lines() {
+            assert!(line.len() <= 70);
+        }
+        assert_eq!(ppm.lines().skip(3).map(|line| line.split(' ').count()).sum::<usize>(), 120);
+    }
+
+    #[test]
+    fn reading_a_plain_ppm() {
+        let data = b"P3\n# made by hand\n2 1\n255\n255 0 0   0 51\n255\n";
+        let c = parse_ppm(data, TransferFunction::Linear).unwrap();
+
+        assert_eq!(c.width(), 2);
+        assert_eq!(c.height(), 1);
+        assert_eq!(c.pixel_at(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(c.pixel_at(1, 0), Color::new(0.0, 0.2, 1.0));
+    }
+
+    #[test]
+    fn header_comments_and_whitespace_are_tolerated() {
+        let data = b"P2#comment\r\n 2\t# width\n1 # height\n\n 15#maxval\n15 0";
+        let c = parse_ppm(data, TransferFunction::Linear).unwrap();
+
+        assert_eq!(c.pixel_at(0, 0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(c.pixel_at(1, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn binary_images_round_trip() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color::new(1.0, 0.2, 0.4));
+        c.write_pixel(2, 1, Color::new(0.6, 0.8, 0.0));
+
+        for depth in [BitDepth::Eight, BitDepth::Sixteen].iter() {
+            let read = parse_ppm(&write(&c, PpmFormat::BinaryRgb, *depth), TransferFunction::Linear).unwrap();
+            assert_eq!(read.canvas, c.canvas);
+        }
+    }
+
+    #[test]
+    fn reading_a_binary_pgm_with_an_odd_maxval() {
+        let data = [b"P5 2 1 1000\n".as_ref(), &[0x03, 0xe8, 0x01, 0xf4]].concat();
+        let c = parse_ppm(&data, TransferFunction::Linear).unwrap();
+
+        assert_eq!(c.pixel_at(0, 0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(c.pixel_at(1, 0), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn malformed_headers_are_described() {
+        let error = |data: &[u8]| parse_ppm(data, TransferFunction::Linear).unwrap_err().to_string();
+
+        assert_eq!(error(b"P7\n1 1\n255\n"), "invalid image: unsupported magic number 'P7'");
+        assert_eq!(error(b"P3\n1 x\n255\n"), "invalid image: invalid height 'x'");
+        assert_eq!(error(b"P3\n1 1\n"), "invalid image: missing maxval");
+        assert_eq!(error(b"P3\n1 1\n70000\n"), "invalid image: maxval 70000 is outside 1..=65535");
+        assert_eq!(error(b"P3\n1 1\n9\n1 2 10\n"), "invalid image: sample 10 exceeds maxval 9");
+        assert_eq!(error(b"P6\n2 1\n255\n\x01\x02"), "invalid image: expected 6 bytes of pixel data, found 2");
+    }
+
+    #[test]
+    fn huge_declared_sizes_fail_before_allocating() {
+        let error = |data: &[u8]| parse_ppm(data, TransferFunction::Linear).unwrap_err().message;
+        let overflowing = format!("P6\n{} 2\n255\n\x01\x02", usize::MAX);
+
+        assert_eq!(error(overflowing.as_bytes()), format!("image size {}x2 is too large", usize::MAX));
+        assert_eq!(
+            error(b"P6\n100000 100000\n65535\n\x01\x02"),
+            "expected 60000000000 bytes of pixel data, found 2"
+        );
+        assert_eq!(
+            error(b"P3\n100000 100000\n255\n1 2 3\n"),
+            "expected 30000000000 samples, but only 7 bytes of data remain"
+        );
+        assert_eq!(parse_ppm(b"P2\n0 100000\n255\n", TransferFunction::Linear).unwrap().height(), 100000);
+    }
+
+    #[test]
+    fn reloading_the_projectile_render() {
+        let c = parse_ppm(include_bytes!("../../eye_candy/projectile.ppm"), TransferFunction::Srgb).unwrap();
+
+        assert_eq!(c.width(), 200);
+        assert_eq!(c.height(), 200);
+        assert!(c.canvas.iter().any(|pixel| *pixel == Color::new(1.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn srgb_output_is_gamma_encoded() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(0.2158605, 0.0, 1.0));
+        c.write_pixel(1, 0, Color::new(0.2158605, 0.2158605, 0.2158605));
+        let mut out = vec![];
+        c.write_ppm(&mut out, PpmFormat::PlainRgb, BitDepth::Eight, OutputEncoding::default()).unwrap();
+        let mut grey = vec![];
+        c.write_ppm(&mut grey, PpmFormat::PlainGrey, BitDepth::Eight, OutputEncoding::default()).unwrap();
+
+        assert_eq!(out, b"P3\n2 1\n255\n128 0 255 128 128 128\n");
+        assert!(grey.ends_with(b" 128\n"));
+    }
+
+    #[test]
+    fn srgb_textures_load_as_linear() {
+        let c = parse_ppm(b"P3 1 1 255 128 0 255", TransferFunction::Srgb).unwrap();
+
+        assert!((c.pixel_at(0, 0).0 - 0.2158605).abs() < 1e-6);
+        assert_eq!(c.pixel_at(0, 0).2, 1.0);
+    }
+
+    #[test]
+    fn writing_a_large_canvas_streams_every_row() {
+        let c = Canvas::new(400, 300);
+        let ppm = write(&c, PpmFormat::BinaryRgb, BitDepth::Eight);
+
+        assert_eq!(ppm.len(), "P6\n400 300\n255\n".len() + 400 * 300 * 3);
+    }
+}
@@ -1,28 +1,135 @@
-use std::ops::{Add, Sub, Mul};
+pub mod tone_map;
+pub mod spaces;
+
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub, Mul, MulAssign, Div};
 use crate::approx_equal::*;
+use tone_map::ToneMap;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Color(pub f64, pub f64, pub f64);
 
+// How stored values relate to linear light. Rendering happens in linear
+// space; sRGB is what image files and displays expect.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TransferFunction {
+    Linear,
+    Srgb
+}
+
+impl TransferFunction {
+    // Linear light to the stored value.
+    pub fn encode(&self, value: f64) -> f64 {
+        match self {
+            TransferFunction::Linear => value,
+            TransferFunction::Srgb => {
+                if value <= 0.0031308 {
+                    value * 12.92
+                } else {
+                    1.055 * value.powf(1.0 / 2.4) - 0.055
+                }
+            }
+        }
+    }
+
+    // A stored value back to linear light.
+    pub fn decode(&self, value: f64) -> f64 {
+        match self {
+            TransferFunction::Linear => value,
+            TransferFunction::Srgb => {
+                if value <= 0.04045 {
+                    value / 12.92
+                } else {
+                    ((value + 0.055) / 1.055).powf(2.4)
+                }
+            }
+        }
+    }
+}
+
 impl Color {
+    pub const BLACK: Color = Color(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color(1.0, 1.0, 1.0);
+    pub const RED: Color = Color(1.0, 0.0, 0.0);
+    pub const GREEN: Color = Color(0.0, 1.0, 0.0);
+    pub const BLUE: Color = Color(0.0, 0.0, 1.0);
+
     pub fn new(red: f64, green: f64, blue: f64) -> Color {
         return Color(red, green, blue);
     }
 
-    fn convert_color_value(normalized_color: f64) -> usize {
+    pub fn min(&self, other: Color) -> Color {
+        Color(self.0.min(other.0), self.1.min(other.1), self.2.min(other.2))
+    }
+
+    pub fn max(&self, other: Color) -> Color {
+        Color(self.0.max(other.0), self.1.max(other.1), self.2.max(other.2))
+    }
+
+    // Clamps each channel separately, e.g. clamp(Color::BLACK, Color::WHITE).
+    pub fn clamp(&self, low: Color, high: Color) -> Color {
+        self.max(low).min(high)
+    }
+
+    // Linear interpolation: t = 0.0 gives self, t = 1.0 gives other.
+    pub fn lerp(&self, other: Color, t: f64) -> Color {
+        *self + (other - *self) * t
+    }
+
+    fn convert_color_value(linear_color: f64, transfer: TransferFunction) -> usize {
+        Color::scale_color_value(transfer.encode(linear_color), 255)
+    }
+
+    pub fn scale_color_value(normalized_color: f64, max_value: usize) -> usize {
         if normalized_color > 1.0 {
-            return 255;
+            return max_value;
         } else if normalized_color < 0.0 {
             return 0;
         }
-        (normalized_color * 255.0).round() as usize
+        (normalized_color * max_value as f64).round() as usize
     }
 
+    // 8-bit sRGB values, as image files and displays expect.
     pub fn integer_color_data(&self) -> [usize; 3] {
+        self.integer_color_data_with(TransferFunction::Srgb)
+    }
+
+    // 8-bit values under a chosen transfer function; Linear skips the gamma
+    // encoding for data that isn't meant to be viewed directly.
+    pub fn integer_color_data_with(&self, transfer: TransferFunction) -> [usize; 3] {
+        [
+            Color::convert_color_value(self.0, transfer),
+            Color::convert_color_value(self.1, transfer),
+            Color::convert_color_value(self.2, transfer)
+        ]
+    }
+
+    // Rec. 709 relative luminance, for linear colors.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.0 + 0.7152 * self.1 + 0.0722 * self.2
+    }
+
+    pub fn to_srgb(&self) -> Color {
+        let srgb = TransferFunction::Srgb;
+        Color(srgb.encode(self.0), srgb.encode(self.1), srgb.encode(self.2))
+    }
+
+    pub fn to_linear(&self) -> Color {
+        let srgb = TransferFunction::Srgb;
+        Color(srgb.decode(self.0), srgb.decode(self.1), srgb.decode(self.2))
+    }
+
+    // Like integer_color_data, but compressing highlights with the tone map
+    // instead of clipping them.
+    pub fn tone_mapped_color_data(&self, tone_map: &ToneMap) -> [usize; 3] {
+        tone_map.apply(*self).integer_color_data()
+    }
+
+    pub fn scaled_color_data(&self, max_value: usize) -> [usize; 3] {
         [
-            Color::convert_color_value(self.0),
-            Color::convert_color_value(self.1),
-            Color::convert_color_value(self.2)
+            Color::scale_color_value(self.0, max_value),
+            Color::scale_color_value(self.1, max_value),
+            Color::scale_color_value(self.2, max_value)
         ]
     }
 }
@@ -84,6 +191,74 @@ impl Mul<f64> for Color {
     }
 }
 
+impl Mul<Color> for f64 {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        other * self
+    }
+}
+
+impl Div for Color {
+    type Output = Color;
+
+    fn div(self, other: Self) -> Color {
+        Color(
+            self.0 / other.0,
+            self.1 / other.1,
+            self.2 / other.2
+        )
+    }
+}
+
+impl Div<f64> for Color {
+    type Output = Color;
+
+    fn div(self, other: f64) -> Color {
+        Color(
+            self.0 / other,
+            self.1 / other,
+            self.2 / other
+        )
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, other: Self) {
+        self.0 += other.0;
+        self.1 += other.1;
+        self.2 += other.2;
+    }
+}
+
+impl MulAssign for Color {
+    fn mul_assign(&mut self, other: Self) {
+        self.0 *= other.0;
+        self.1 *= other.1;
+        self.2 *= other.2;
+    }
+}
+
+impl MulAssign<f64> for Color {
+    fn mul_assign(&mut self, other: f64) {
+        self.0 *= other;
+        self.1 *= other;
+        self.2 *= other;
+    }
+}
+
+impl Sum for Color {
+    fn sum<I: Iterator<Item = Color>>(iter: I) -> Color {
+        iter.fold(Color::BLACK, |total, color| total + color)
+    }
+}
+
+impl<'a> Sum<&'a Color> for Color {
+    fn sum<I: Iterator<Item = &'a Color>>(iter: I) -> Color {
+        iter.fold(Color::BLACK, |total, color| total + *color)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,13 +307,121 @@ mod tests {
         assert_eq!(c1 * c2, expected);
     }
 
+    #[test]
+    fn dividing_colors() {
+        let c1 = Color::new(0.9, 0.6, 0.75);
+        let c2 = Color::new(0.3, 0.2, 0.5);
+
+        assert_eq!(c1 / c2, Color::new(3.0, 3.0, 1.5));
+        assert_eq!(c1 / 3.0, Color::new(0.3, 0.2, 0.25));
+    }
+
+    #[test]
+    fn multiplying_scalar_by_color() {
+        assert_eq!(2.0 * Color::new(0.2, 0.3, 0.4), Color::new(0.4, 0.6, 0.8));
+    }
+
+    #[test]
+    fn accumulating_colors_in_place() {
+        let mut c = Color::BLACK;
+        c += Color::new(0.5, 0.25, 1.0);
+        c += Color::new(0.5, 0.25, 1.0);
+        assert_eq!(c, Color::new(1.0, 0.5, 2.0));
+
+        c *= Color::new(0.5, 2.0, 0.0);
+        assert_eq!(c, Color::new(0.5, 1.0, 0.0));
+        c *= 3.0;
+        assert_eq!(c, Color::new(1.5, 3.0, 0.0));
+    }
+
+    #[test]
+    fn summing_colors() {
+        let samples = vec![Color::RED, Color::GREEN, Color::BLUE];
+
+        assert_eq!(samples.iter().sum::<Color>(), Color::WHITE);
+        assert_eq!(samples.into_iter().sum::<Color>() / 3.0, Color::new(1.0, 1.0, 1.0) / 3.0);
+        assert_eq!(Vec::<Color>::new().into_iter().sum::<Color>(), Color::BLACK);
+    }
+
+    #[test]
+    fn component_wise_min_max_and_clamp() {
+        let a = Color::new(-0.5, 0.4, 1.7);
+        let b = Color::new(0.2, 0.2, 0.2);
+
+        assert_eq!(a.min(b), Color::new(-0.5, 0.2, 0.2));
+        assert_eq!(a.max(b), Color::new(0.2, 0.4, 1.7));
+        assert_eq!(a.clamp(Color::BLACK, Color::WHITE), Color::new(0.0, 0.4, 1.0));
+    }
+
+    #[test]
+    fn interpolating_colors() {
+        let from = Color::new(0.0, 0.5, 1.0);
+        let to = Color::new(1.0, 0.5, 0.0);
+
+        assert_eq!(from.lerp(to, 0.0), from);
+        assert_eq!(from.lerp(to, 1.0), to);
+        assert_eq!(from.lerp(to, 0.25), Color::new(0.25, 0.5, 0.75));
+    }
+
     #[test]
     fn scaling_color_val() {
-        assert_eq!(Color::convert_color_value(1.1), 255);
-        assert_eq!(Color::convert_color_value(1.0), 255);
-        assert_eq!(Color::convert_color_value(0.0), 0);
-        assert_eq!(Color::convert_color_value(-1.0), 0);
-        assert_eq!(Color::convert_color_value(0.99999), 255);
-        assert_eq!(Color::convert_color_value(0.5), 128);
+        let linear = TransferFunction::Linear;
+        assert_eq!(Color::convert_color_value(1.1, linear), 255);
+        assert_eq!(Color::convert_color_value(1.0, linear), 255);
+        assert_eq!(Color::convert_color_value(0.0, linear), 0);
+        assert_eq!(Color::convert_color_value(-1.0, linear), 0);
+        assert_eq!(Color::convert_color_value(0.99999, linear), 255);
+        assert_eq!(Color::convert_color_value(0.5, linear), 128);
+    }
+
+    #[test]
+    fn integer_color_data_is_srgb_unless_linear_is_asked_for() {
+        let c = Color::new(0.5, 0.2158605, 1.5);
+
+        assert_eq!(Color::convert_color_value(0.5, TransferFunction::Srgb), 188);
+        assert_eq!(c.integer_color_data(), [188, 128, 255]);
+        assert_eq!(c.integer_color_data_with(TransferFunction::Linear), [128, 55, 255]);
+    }
+
+    #[test]
+    fn converting_between_linear_and_srgb() {
+        let linear = Color::new(0.0, 0.214041, 1.0);
+        let srgb = Color::new(0.0, 0.5, 1.0);
+
+        assert_eq!(linear.to_srgb(), srgb);
+        assert_eq!(srgb.to_linear(), linear);
+        assert!((TransferFunction::Srgb.encode(0.001) - 0.01292).abs() < 1e-12);
+        assert!((TransferFunction::Srgb.decode(0.01292) - 0.001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn computing_luminance() {
+        assert_eq!(Color::new(1.0, 1.0, 1.0).luminance(), 1.0);
+        assert_eq!(Color::new(0.0, 1.0, 0.0).luminance(), 0.7152);
+        assert_eq!(Color::new(0.5, 0.0, 1.0).luminance(), 0.1785);
+    }
+
+    #[test]
+    fn srgb_round_trips() {
+        for i in 0..=20 {
+            let value = i as f64 / 20.0;
+            let srgb = TransferFunction::Srgb;
+            assert!((srgb.decode(srgb.encode(value)) - value).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn tone_mapping_color_data() {
+        let tone_map = ToneMap::new(tone_map::ToneMapOperator::Reinhard).unwrap();
+
+        assert_eq!(Color::new(1.0, 3.0, 0.0).tone_mapped_color_data(&tone_map), [188, 225, 0]);
+    }
+
+    #[test]
+    fn scaling_color_val_to_other_depths() {
+        assert_eq!(Color::scale_color_value(1.5, 65535), 65535);
+        assert_eq!(Color::scale_color_value(0.5, 65535), 32768);
+        assert_eq!(Color::scale_color_value(-0.5, 65535), 0);
+        assert_eq!(Color::new(1.0, 0.5, 0.0).scaled_color_data(15), [15, 8, 0]);
     }
 }
\ No newline at end of file
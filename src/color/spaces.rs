@@ -0,0 +1,215 @@
+use std::fmt;
+use crate::color::Color;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorParseError {
+    pub message: String
+}
+
+impl ColorParseError {
+    fn new(message: String) -> ColorParseError {
+        ColorParseError {
+            message
+        }
+    }
+}
+
+impl fmt::Display for ColorParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid color: {}", self.message)
+    }
+}
+
+impl std::error::Error for ColorParseError {}
+
+// Hue in degrees plus the channel offsets shared by HSV and HSL.
+fn hue_and_range(color: &Color) -> (f64, f64, f64) {
+    let max = color.0.max(color.1).max(color.2);
+    let min = color.0.min(color.1).min(color.2);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == color.0 {
+        60.0 * ((color.1 - color.2) / delta).rem_euclid(6.0)
+    } else if max == color.1 {
+        60.0 * ((color.2 - color.0) / delta + 2.0)
+    } else {
+        60.0 * ((color.0 - color.1) / delta + 4.0)
+    };
+    (hue, min, max)
+}
+
+// Builds a color from a hue and chroma, lifting every channel by m.
+fn from_hue_and_chroma(hue: f64, chroma: f64, m: f64) -> Color {
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x)
+    };
+    Color::new(r + m, g + m, b + m)
+}
+
+// CIE 1931 xy chromaticity of a blackbody, from Kim et al.'s cubic fit to
+// the Planckian locus (valid from 1667K to 25000K).
+fn planckian_chromaticity(kelvin: f64) -> (f64, f64) {
+    let t = kelvin.clamp(1667.0, 25000.0);
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t.powi(3) - 0.2343589e6 / t.powi(2) + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t.powi(3) + 2.1070379e6 / t.powi(2) + 0.2226347e3 / t + 0.240390
+    };
+    let y = if t <= 2222.0 {
+        -1.1063814 * x.powi(3) - 1.34811020 * x.powi(2) + 2.18555832 * x - 0.20219683
+    } else if t <= 4000.0 {
+        -0.9549476 * x.powi(3) - 1.37418593 * x.powi(2) + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x.powi(3) - 5.87338670 * x.powi(2) + 3.75112997 * x - 0.37001483
+    };
+    (x, y)
+}
+
+// HSV and HSL describe sRGB-encoded colors, like hex codes and color pickers,
+// so linear colors are encoded before converting and decoded after.
+impl Color {
+    // Hue in degrees, saturation and value in 0.0..=1.0.
+    pub fn to_hsv(&self) -> (f64, f64, f64) {
+        let (hue, min, max) = hue_and_range(&self.to_srgb());
+        let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+        (hue, saturation, max)
+    }
+
+    pub fn from_hsv(hue: f64, saturation: f64, value: f64) -> Color {
+        let chroma = value * saturation;
+        from_hue_and_chroma(hue, chroma, value - chroma).to_linear()
+    }
+
+    // Hue in degrees, saturation and lightness in 0.0..=1.0.
+    pub fn to_hsl(&self) -> (f64, f64, f64) {
+        let (hue, min, max) = hue_and_range(&self.to_srgb());
+        let lightness = (max + min) / 2.0;
+        let saturation = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+        };
+        (hue, saturation, lightness)
+    }
+
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64) -> Color {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        from_hue_and_chroma(hue, chroma, lightness - chroma / 2.0).to_linear()
+    }
+
+    // Parses "#rrggbb" (the '#' is optional). Hex codes are sRGB, so the
+    // result is converted to linear for rendering.
+    pub fn from_hex(hex: &str) -> Result<Color, ColorParseError> {
+        let digits = hex.trim().trim_start_matches('#');
+        if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ColorParseError::new(format!("'{}' is not of the form #rrggbb", hex)));
+        }
+        let channel = |start: usize| u8::from_str_radix(&digits[start..start + 2], 16).unwrap() as f64 / 255.0;
+        Ok(Color::new(channel(0), channel(2), channel(4)).to_linear())
+    }
+
+    // The sRGB "#rrggbb" form of a linear color, clamped to 0.0..=1.0.
+    pub fn to_hex(&self) -> String {
+        let [r, g, b] = self.integer_color_data();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    // The linear color of a blackbody radiator at the given temperature,
+    // scaled so its brightest channel is 1.0. The sRGB white point sits just
+    // off the blackbody curve, so 6504K comes out faintly magenta.
+    pub fn from_kelvin(kelvin: f64) -> Color {
+        let (x, y) = planckian_chromaticity(kelvin);
+        let (big_x, big_y, big_z) = (x / y, 1.0, (1.0 - x - y) / y);
+        let r = 3.2404542 * big_x - 1.5371385 * big_y - 0.4985314 * big_z;
+        let g = -0.9692660 * big_x + 1.8760108 * big_y + 0.0415560 * big_z;
+        let b = 0.0556434 * big_x - 0.2040259 * big_y + 1.0572252 * big_z;
+        let (r, g, b) = (r.max(0.0), g.max(0.0), b.max(0.0));
+        let max = r.max(g).max(b);
+        Color::new(r / max, g / max, b / max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Color, b: Color, tolerance: f64) -> bool {
+        (a.0 - b.0).abs() < tolerance && (a.1 - b.1).abs() < tolerance && (a.2 - b.2).abs() < tolerance
+    }
+
+    fn close_triple(a: (f64, f64, f64), b: (f64, f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9 && (a.2 - b.2).abs() < 1e-9
+    }
+
+    #[test]
+    fn converting_to_hsv() {
+        let hsv = |r: f64, g: f64, b: f64| Color::new(r, g, b).to_linear().to_hsv();
+
+        assert!(close_triple(hsv(1.0, 0.0, 0.0), (0.0, 1.0, 1.0)));
+        assert!(close_triple(hsv(0.0, 0.5, 0.5), (180.0, 1.0, 0.5)));
+        assert!(close_triple(hsv(1.0, 0.0, 0.5), (330.0, 1.0, 1.0)));
+        assert!(close_triple(hsv(0.4, 0.4, 0.4), (0.0, 0.0, 0.4)));
+    }
+
+    #[test]
+    fn converting_from_hsv() {
+        assert_eq!(Color::from_hsv(120.0, 1.0, 1.0), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(Color::from_hsv(270.0, 0.5, 0.8), Color::new(0.6, 0.4, 0.8).to_linear());
+        assert_eq!(Color::from_hsv(-90.0, 0.5, 0.8), Color::new(0.6, 0.4, 0.8).to_linear());
+    }
+
+    #[test]
+    fn hsv_and_hex_agree() {
+        assert_eq!(Color::from_hex("#ff8000").unwrap(), Color::from_hsv(30.0 * 256.0 / 255.0, 1.0, 1.0));
+        assert_eq!(Color::from_hex("#336699").unwrap(), Color::from_hsl(210.0, 0.5, 0.4));
+    }
+
+    #[test]
+    fn converting_to_and_from_hsl() {
+        assert!(close_triple(Color::new(0.0, 0.0, 1.0).to_hsl(), (240.0, 1.0, 0.5)));
+        assert_eq!(Color::from_hsl(60.0, 1.0, 0.25), Color::new(0.5, 0.5, 0.0).to_linear());
+        assert_eq!(Color::from_hsl(0.0, 0.0, 0.75), Color::new(0.75, 0.75, 0.75).to_linear());
+
+        let color = Color::new(0.2, 0.7, 0.4);
+        let (h, s, l) = color.to_hsl();
+        assert_eq!(Color::from_hsl(h, s, l), color);
+        let (h, s, v) = color.to_hsv();
+        assert_eq!(Color::from_hsv(h, s, v), color);
+    }
+
+    #[test]
+    fn parsing_hex_colors() {
+        assert_eq!(Color::from_hex("#ff0000"), Ok(Color::new(1.0, 0.0, 0.0)));
+        assert_eq!(Color::from_hex("80FF00"), Ok(Color::new(0.2158605, 1.0, 0.0)));
+        assert_eq!(Color::new(0.2158605, 1.0, 0.0).to_hex(), "#80ff00");
+    }
+
+    #[test]
+    fn malformed_hex_colors_are_errors() {
+        let error = Color::from_hex("#12345g").unwrap_err();
+
+        assert_eq!(error.to_string(), "invalid color: '#12345g' is not of the form #rrggbb");
+        assert!(Color::from_hex("#fff").is_err());
+    }
+
+    #[test]
+    fn blackbody_colors() {
+        let tungsten = Color::from_kelvin(3200.0);
+        let daylight = Color::from_kelvin(6504.0);
+        let sky = Color::from_kelvin(12000.0);
+
+        assert_eq!(tungsten.0, 1.0);
+        assert!(tungsten.1 < 1.0 && tungsten.2 < tungsten.1);
+        assert!(close(daylight, Color::new(1.0, 0.94, 0.99), 0.01));
+        assert_eq!(sky.2, 1.0);
+        assert!(sky.0 < sky.1);
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+use crate::color::Color;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ToneMapOperator {
+    // Values above 1.0 simply saturate.
+    Clamp,
+    // c / (1 + c): compresses everything, never quite reaching white.
+    Reinhard,
+    // Reinhard with the given value mapping to pure white.
+    ExtendedReinhard { white_point: f64 },
+    // Narkowicz's fit of the ACES filmic curve.
+    AcesFilmic
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToneMapError {
+    pub message: String
+}
+
+impl fmt::Display for ToneMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid tone map: {}", self.message)
+    }
+}
+
+impl std::error::Error for ToneMapError {}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ToneMap {
+    operator: ToneMapOperator,
+    // Scales the input before mapping; 2.0 is one stop brighter.
+    exposure: f64
+}
+
+impl ToneMap {
+    // An extended Reinhard white point must be positive and finite, or the
+    // curve divides by zero.
+    pub fn new(operator: ToneMapOperator) -> Result<ToneMap, ToneMapError> {
+        if let ToneMapOperator::ExtendedReinhard { white_point } = operator {
+            if white_point <= 0.0 || !white_point.is_finite() {
+                return Err(ToneMapError {
+                    message: format!("white point {} is not a positive number", white_point)
+                });
+            }
+        }
+        Ok(ToneMap {
+            operator,
+            exposure: 1.0
+        })
+    }
+
+    // Anything but a positive, finite scale would turn the image black or NaN.
+    pub fn with_exposure(self, exposure: f64) -> Result<ToneMap, ToneMapError> {
+        if exposure <= 0.0 || !exposure.is_finite() {
+            return Err(ToneMapError {
+                message: format!("exposure {} is not a positive number", exposure)
+            });
+        }
+        Ok(ToneMap {
+            exposure,
+            ..self
+        })
+    }
+
+    pub fn operator(&self) -> ToneMapOperator {
+        self.operator
+    }
+
+    pub fn exposure(&self) -> f64 {
+        self.exposure
+    }
+
+    fn map_channel(&self, value: f64) -> f64 {
+        let x = value.max(0.0) * self.exposure;
+        let mapped = match self.operator {
+            ToneMapOperator::Clamp => x,
+            ToneMapOperator::Reinhard => x / (1.0 + x),
+            ToneMapOperator::ExtendedReinhard { white_point } => {
+                x * (1.0 + x / (white_point * white_point)) / (1.0 + x)
+            }
+            ToneMapOperator::AcesFilmic => {
+                (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+            }
+        };
+        mapped.min(1.0)
+    }
+
+    // Maps an unbounded linear color into 0.0..=1.0.
+    pub fn apply(&self, color: Color) -> Color {
+        Color::new(
+            self.map_channel(color.0),
+            self.map_channel(color.1),
+            self.map_channel(color.2)
+        )
+    }
+}
+
+impl Default for ToneMap {
+    fn default() -> ToneMap {
+        ToneMap {
+            operator: ToneMapOperator::Clamp,
+            exposure: 1.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamping_saturates_bright_values() {
+        let tone_map = ToneMap::default();
+
+        assert_eq!(tone_map.apply(Color::new(-0.5, 0.25, 4.0)), Color::new(0.0, 0.25, 1.0));
+    }
+
+    #[test]
+    fn reinhard_compresses_without_reaching_white() {
+        let tone_map = ToneMap::new(ToneMapOperator::Reinhard).unwrap();
+        let mapped = tone_map.apply(Color::new(1.0, 3.0, 1000.0));
+
+        assert_eq!(mapped, Color::new(0.5, 0.75, 1000.0 / 1001.0));
+        assert!(mapped.2 < 1.0);
+    }
+
+    #[test]
+    fn extended_reinhard_maps_the_white_point_to_one() {
+        let tone_map = ToneMap::new(ToneMapOperator::ExtendedReinhard { white_point: 4.0 }).unwrap();
+        let mapped = tone_map.apply(Color::new(4.0, 1.0, 10.0));
+
+        assert_eq!(mapped, Color::new(1.0, (1.0 + 1.0 / 16.0) / 2.0, 1.0));
+    }
+
+    #[test]
+    fn aces_filmic_is_black_at_zero_and_saturates() {
+        let tone_map = ToneMap::new(ToneMapOperator::AcesFilmic).unwrap();
+        let mapped = tone_map.apply(Color::new(0.0, 0.18, 100.0));
+
+        assert_eq!(mapped.0, 0.0);
+        assert!((mapped.1 - 0.267).abs() < 0.001);
+        assert_eq!(mapped.2, 1.0);
+    }
+
+    #[test]
+    fn exposure_scales_the_input() {
+        let reinhard = ToneMap::new(ToneMapOperator::Reinhard).unwrap();
+        let brighter = reinhard.with_exposure(2.0).unwrap();
+        let darker = reinhard.with_exposure(0.25).unwrap();
+
+        assert_eq!(reinhard.exposure(), 1.0);
+        assert_eq!(brighter.apply(Color::new(0.5, 0.0, 0.0)), Color::new(0.5, 0.0, 0.0));
+        assert_eq!(darker.apply(Color::new(4.0, 0.0, 0.0)), Color::new(0.5, 0.0, 0.0));
+        assert_eq!(darker.operator(), ToneMapOperator::Reinhard);
+    }
+
+    #[test]
+    fn exposure_must_be_positive() {
+        let error = |exposure: f64| ToneMap::default().with_exposure(exposure).unwrap_err();
+
+        assert_eq!(error(0.0).to_string(), "invalid tone map: exposure 0 is not a positive number");
+        assert_eq!(error(-1.0).message, "exposure -1 is not a positive number");
+        assert!(ToneMap::default().with_exposure(f64::NAN).is_err());
+        assert!(ToneMap::default().with_exposure(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn a_white_point_must_be_positive() {
+        let error = |white_point: f64| ToneMap::new(ToneMapOperator::ExtendedReinhard { white_point }).unwrap_err();
+
+        assert_eq!(error(0.0).message, "white point 0 is not a positive number");
+        assert_eq!(error(-2.0).to_string(), "invalid tone map: white point -2 is not a positive number");
+        assert!(ToneMap::new(ToneMapOperator::ExtendedReinhard { white_point: f64::NAN }).is_err());
+        assert!(ToneMap::new(ToneMapOperator::ExtendedReinhard { white_point: f64::INFINITY }).is_err());
+        assert_eq!(
+            ToneMap::new(ToneMapOperator::ExtendedReinhard { white_point: 0.5 }).unwrap().operator(),
+            ToneMapOperator::ExtendedReinhard { white_point: 0.5 }
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use crate::shapes::Shape;
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::matrix::Matrix;
+use crate::matrix::matrix4::Matrix4;
+
+// A hit on the primitive that was actually struck, which for instances and
+// groups is a child rather than the shape the ray was cast at. to_object
+// takes world points down into that primitive's object space; u and v are
+// surface coordinates for shapes that have them (barycentric on triangles).
+#[derive(Copy, Clone)]
+pub struct Intersection<'a> {
+    pub t: f64,
+    pub object: &'a dyn Shape,
+    pub u: f64,
+    pub v: f64,
+    to_object: Matrix4
+}
+
+impl<'a> Intersection<'a> {
+    pub fn new(t: f64, object: &'a dyn Shape) -> Intersection<'a> {
+        Intersection::with_uv(t, object, 0.0, 0.0)
+    }
+
+    pub fn with_uv(t: f64, object: &'a dyn Shape, u: f64, v: f64) -> Intersection<'a> {
+        Intersection {
+            t,
+            object,
+            u,
+            v,
+            to_object: Matrix4::identity()
+        }
+    }
+
+    // Used by a parent as the hit passes up through it: points now reach the
+    // object through the parent's inverse first.
+    pub fn transformed(self, inverse: Matrix4) -> Intersection<'a> {
+        Intersection {
+            to_object: self.to_object * inverse,
+            ..self
+        }
+    }
+
+    // Through every parent transform, outermost first.
+    pub fn world_to_object(&self, point: Point3) -> Point3 {
+        self.to_object * point
+    }
+
+    // Back up through the parents with the inverse transpose of each.
+    pub fn normal_to_world(&self, normal: Vector3) -> Vector3 {
+        (self.to_object.transpose() * normal).normalize()
+    }
+
+    pub fn normal_at(&self, point: Point3) -> Vector3 {
+        let object_normal = self.object.local_normal_at_hit(self.world_to_object(point), self);
+        self.normal_to_world(object_normal)
+    }
+}
+
+// The visible hit: the nearest one not behind the ray origin.
+pub fn hit<'a>(intersections: &[Intersection<'a>]) -> Option<Intersection<'a>> {
+    intersections.iter()
+        .filter(|intersection| intersection.t >= 0.0)
+        .min_by(|a, b| a.t.partial_cmp(&b.t).unwrap())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::triangle::Triangle;
+
+    fn triangle() -> Triangle {
+        Triangle::new(Point3::new(0.0, 1.0, 0.0), Point3::new(-1.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0))
+    }
+
+    #[test]
+    fn an_intersection_starts_without_uv_or_transform() {
+        let t = triangle();
+        let i = Intersection::new(3.5, &t);
+
+        assert_eq!(i.t, 3.5);
+        assert_eq!((i.u, i.v), (0.0, 0.0));
+        assert_eq!(i.normal_at(Point3::new(0.0, 0.5, 0.0)), Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn the_hit_is_the_lowest_nonnegative_t() {
+        let t = triangle();
+        let hits = vec![
+            Intersection::new(5.0, &t),
+            Intersection::new(-3.0, &t),
+            Intersection::new(2.0, &t),
+            Intersection::new(7.0, &t)
+        ];
+
+        assert_eq!(hit(&hits).unwrap().t, 2.0);
+        assert!(hit(&hits[1..2]).is_none());
+        assert!(hit(&[]).is_none());
+    }
+
+    #[test]
+    fn normals_pass_through_a_parent_transform() {
+        let t = triangle();
+        // A quarter turn about x takes the triangle's -z normal to +y. The
+        // turn's inverse is its transpose.
+        let rotation = Matrix4::new([
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, -1.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ]);
+        let i = Intersection::new(1.0, &t).transformed(rotation.transpose());
+
+        assert_eq!(i.normal_at(Point3::new(0.0, 0.0, 0.5)), Vector3::new(0.0, 1.0, 0.0));
+    }
+}
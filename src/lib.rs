@@ -3,4 +3,11 @@ pub mod point3;
 pub mod vector3;
 pub mod color;
 pub mod matrix;
-pub mod canvas;
\ No newline at end of file
+pub mod canvas;
+pub mod mesh;
+pub mod ray;
+pub mod intersection;
+pub mod bounding_box;
+pub mod bvh;
+pub mod roots;
+pub mod shapes;
\ No newline at end of file
@@ -40,6 +40,14 @@ impl Matrix3 {
             return -self.minor(row, col);
         }
     }
+
+    pub fn determinant(&self) -> f64 {
+        let mut det = 0.0;
+        for col in 0..3 {
+            det += self.element(0, col) * self.cofactor(0, col);
+        }
+        det
+    }
 }
 
 impl PartialEq for Matrix3 {
@@ -40,6 +40,45 @@ impl Matrix4 {
         }
         sub
     }
+
+    fn minor(&self, row: usize, col: usize) -> f64 {
+        self.submatrix(row, col).determinant()
+    }
+
+    fn cofactor(&self, row: usize, col: usize) -> f64 {
+        if (row + col).is_multiple_of(2) {
+            self.minor(row, col)
+        } else {
+            -self.minor(row, col)
+        }
+    }
+
+    pub fn determinant(&self) -> f64 {
+        let mut det = 0.0;
+        for col in 0..4 {
+            det += self.element(0, col) * self.cofactor(0, col);
+        }
+        det
+    }
+
+    pub fn is_invertible(&self) -> bool {
+        !approx_equal::equal(self.determinant(), 0.0)
+    }
+
+    pub fn inverse(&self) -> Option<Matrix4> {
+        if !self.is_invertible() {
+            return None;
+        }
+        let det = self.determinant();
+        let mut inverse = Matrix4::new([0.0; 16]);
+        for row in 0..4 {
+            for col in 0..4 {
+                // Writing to (col, row) transposes the cofactor matrix.
+                inverse.write(col, row, self.cofactor(row, col) / det);
+            }
+        }
+        Some(inverse)
+    }
 }
 
 impl PartialEq for Matrix4 {
@@ -323,6 +362,80 @@ mod tests {
             -6.0, 7.0, 7.0, -9.0
         ]);
 
-        
+        assert_eq!(a.cofactor(0, 0), 690.0);
+        assert_eq!(a.cofactor(0, 1), 447.0);
+        assert_eq!(a.cofactor(0, 2), 210.0);
+        assert_eq!(a.cofactor(0, 3), 51.0);
+        assert_eq!(a.determinant(), -4071.0);
+    }
+
+    #[test]
+    fn testing_an_invertible_matrix_for_invertibility() {
+        let a = Matrix4::new([
+            6.0, 4.0, 4.0, 4.0,
+            5.0, 5.0, 7.0, 6.0,
+            4.0, -9.0, 3.0, -7.0,
+            9.0, 1.0, 7.0, -6.0
+        ]);
+
+        assert_eq!(a.determinant(), -2120.0);
+        assert!(a.is_invertible());
+    }
+
+    #[test]
+    fn testing_a_noninvertible_matrix_for_invertibility() {
+        let a = Matrix4::new([
+            -4.0, 2.0, -2.0, -3.0,
+            9.0, 6.0, 2.0, 6.0,
+            0.0, -5.0, 1.0, -5.0,
+            0.0, 0.0, 0.0, 0.0
+        ]);
+
+        assert_eq!(a.determinant(), 0.0);
+        assert!(!a.is_invertible());
+        assert!(a.inverse().is_none());
+    }
+
+    #[test]
+    fn calculating_the_inverse_of_a_matrix() {
+        let a = Matrix4::new([
+            -5.0, 2.0, 6.0, -8.0,
+            1.0, -5.0, 1.0, 8.0,
+            7.0, 7.0, -6.0, -7.0,
+            1.0, -3.0, 7.0, 4.0
+        ]);
+        let b = a.inverse().unwrap();
+        let expected = Matrix4::new([
+            0.21805, 0.45113, 0.24060, -0.04511,
+            -0.80827, -1.45677, -0.44361, 0.52068,
+            -0.07895, -0.22368, -0.05263, 0.19737,
+            -0.52256, -0.81391, -0.30075, 0.30639
+        ]);
+
+        assert_eq!(a.determinant(), 532.0);
+        assert_eq!(a.cofactor(2, 3), -160.0);
+        assert_eq!(b.element(3, 2), -160.0 / 532.0);
+        assert_eq!(a.cofactor(3, 2), 105.0);
+        assert_eq!(b.element(2, 3), 105.0 / 532.0);
+        assert_eq!(b, expected);
+    }
+
+    #[test]
+    fn multiplying_a_product_by_its_inverse() {
+        let a = Matrix4::new([
+            3.0, -9.0, 7.0, 3.0,
+            3.0, -8.0, 2.0, -9.0,
+            -4.0, 4.0, 4.0, 1.0,
+            -6.0, 5.0, -1.0, 1.0
+        ]);
+        let b = Matrix4::new([
+            8.0, 2.0, 2.0, 2.0,
+            3.0, -1.0, 7.0, 0.0,
+            7.0, 0.0, 5.0, 4.0,
+            6.0, -2.0, 0.0, 5.0
+        ]);
+        let c = a * b;
+
+        assert_eq!(c * b.inverse().unwrap(), a);
     }
 }
\ No newline at end of file
@@ -0,0 +1,188 @@
+pub mod obj;
+pub mod stl;
+pub mod ply;
+pub mod normals;
+pub mod subdivision;
+
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::bounding_box::BoundingBox;
+use crate::matrix::Matrix;
+use crate::matrix::matrix4::Matrix4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshTriangle {
+    pub vertices: [usize; 3],
+    pub texture_coords: Option<[usize; 3]>,
+    pub normals: Option<[usize; 3]>
+}
+
+impl MeshTriangle {
+    pub fn new(vertices: [usize; 3]) -> MeshTriangle {
+        MeshTriangle {
+            vertices,
+            texture_coords: None,
+            normals: None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshGroup {
+    pub name: String,
+    pub triangles: Vec<MeshTriangle>,
+    pub children: Vec<MeshGroup>
+}
+
+impl MeshGroup {
+    pub fn new(name: &str) -> MeshGroup {
+        MeshGroup {
+            name: String::from(name),
+            triangles: vec![],
+            children: vec![]
+        }
+    }
+
+    pub fn child(&self, name: &str) -> Option<&MeshGroup> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len() +
+        self.children.iter().map(|child| child.triangle_count()).sum::<usize>()
+    }
+}
+
+// Vertex data is shared by every group; triangles index into it (zero based).
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub vertices: Vec<Point3>,
+    pub texture_coords: Vec<(f64, f64)>,
+    pub normals: Vec<Vector3>,
+    pub root: MeshGroup
+}
+
+impl Mesh {
+    pub fn new() -> Mesh {
+        Mesh {
+            vertices: vec![],
+            texture_coords: vec![],
+            normals: vec![],
+            root: MeshGroup::new("")
+        }
+    }
+
+    pub fn triangle_points(&self, triangle: &MeshTriangle) -> [Point3; 3] {
+        [
+            self.vertices[triangle.vertices[0]],
+            self.vertices[triangle.vertices[1]],
+            self.vertices[triangle.vertices[2]]
+        ]
+    }
+
+    // Bakes a transform into the vertex data. Normals go through the inverse
+    // transpose so they stay perpendicular to the surface; None when the
+    // transform can't be inverted, e.g. a zero scale.
+    pub fn transformed(&self, matrix: Matrix4) -> Option<Mesh> {
+        let normal_matrix = matrix.inverse()?.transpose();
+        Some(Mesh {
+            vertices: self.vertices.iter().map(|vertex| matrix * *vertex).collect(),
+            texture_coords: self.texture_coords.clone(),
+            normals: self.normals.iter().map(|normal| (normal_matrix * *normal).normalize()).collect(),
+            root: self.root.clone()
+        })
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        for vertex in self.vertices.iter() {
+            bounds.add_point(*vertex);
+        }
+        bounds
+    }
+}
+
+impl Default for Mesh {
+    fn default() -> Self {
+        Mesh::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counting_triangles_through_child_groups() {
+        let mut root = MeshGroup::new("");
+        let mut child = MeshGroup::new("child");
+        root.triangles.push(MeshTriangle::new([0, 1, 2]));
+        child.triangles.push(MeshTriangle::new([0, 2, 3]));
+        child.triangles.push(MeshTriangle::new([0, 3, 4]));
+        root.children.push(child);
+
+        assert_eq!(root.triangle_count(), 3);
+        assert_eq!(root.child("child").unwrap().triangle_count(), 2);
+        assert!(root.child("missing").is_none());
+    }
+
+    #[test]
+    fn looking_up_triangle_points() {
+        let mut mesh = Mesh::new();
+        mesh.vertices.push(Point3::new(0.0, 1.0, 0.0));
+        mesh.vertices.push(Point3::new(-1.0, 0.0, 0.0));
+        mesh.vertices.push(Point3::new(1.0, 0.0, 0.0));
+        let points = mesh.triangle_points(&MeshTriangle::new([2, 0, 1]));
+
+        assert_eq!(points[0], Point3::new(1.0, 0.0, 0.0));
+        assert_eq!(points[1], Point3::new(0.0, 1.0, 0.0));
+        assert_eq!(points[2], Point3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn baking_a_transform_into_a_mesh() {
+        let mut mesh = Mesh::new();
+        mesh.vertices.push(Point3::new(1.0, 1.0, 0.0));
+        mesh.normals.push(Vector3::new(0.0, 1.0, 0.0));
+        mesh.normals.push(Vector3::new(1.0, 1.0, 0.0).normalize());
+        mesh.root.triangles.push(MeshTriangle::new([0, 0, 0]));
+        let matrix = Matrix4::new([
+            2.0, 0.0, 0.0, 3.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ]);
+        let baked = mesh.transformed(matrix).unwrap();
+
+        assert_eq!(baked.vertices[0], Point3::new(5.0, 1.0, 0.0));
+        assert_eq!(baked.normals[0], Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(baked.normals[1], Vector3::new(0.5, 1.0, 0.0).normalize());
+        assert_eq!(baked.root, mesh.root);
+    }
+
+    #[test]
+    fn a_singular_transform_cannot_be_baked() {
+        let mut mesh = Mesh::new();
+        mesh.vertices.push(Point3::new(1.0, 1.0, 0.0));
+        let flatten = Matrix4::new([
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ]);
+
+        assert!(mesh.transformed(flatten).is_none());
+    }
+
+    #[test]
+    fn mesh_bounds_enclose_every_vertex() {
+        let mut mesh = Mesh::new();
+        mesh.vertices.push(Point3::new(0.0, 1.0, -2.0));
+        mesh.vertices.push(Point3::new(-1.0, 0.0, 3.0));
+        mesh.vertices.push(Point3::new(4.0, -5.0, 0.0));
+        let bounds = mesh.bounds();
+
+        assert_eq!(bounds.min, Point3::new(-1.0, -5.0, -2.0));
+        assert_eq!(bounds.max, Point3::new(4.0, 1.0, 3.0));
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use crate::vector3::Vector3;
+use crate::mesh::{Mesh, MeshGroup, MeshTriangle};
+
+const EPSILON: f64 = 0.00001;
+
+pub(crate) fn collect_triangles(group: &MeshGroup, triangles: &mut Vec<MeshTriangle>) {
+    triangles.extend_from_slice(&group.triangles);
+    for child in group.children.iter() {
+        collect_triangles(child, triangles);
+    }
+}
+
+// Visits triangles in the same order collect_triangles returns them.
+pub(crate) fn for_each_triangle_mut<F: FnMut(&mut MeshTriangle)>(group: &mut MeshGroup, f: &mut F) {
+    for triangle in group.triangles.iter_mut() {
+        f(triangle);
+    }
+    for child in group.children.iter_mut() {
+        for_each_triangle_mut(child, f);
+    }
+}
+
+// Cross product of two edges: points along the face normal, with a length of
+// twice the triangle's area.
+fn area_normal(mesh: &Mesh, triangle: &MeshTriangle) -> Vector3 {
+    let [p1, p2, p3] = mesh.triangle_points(triangle);
+    (p2 - p1).cross(&(p3 - p1))
+}
+
+// Replaces the mesh's normals with smooth per-vertex ones. At each corner the
+// normals of the faces sharing that vertex are averaged, weighted by area, but
+// only faces within crease_angle (radians) of the corner's own face count, so
+// hard edges stay sharp. Degenerate triangles are left without normals.
+pub fn generate_normals(mesh: &Mesh, crease_angle: f64) -> Mesh {
+    let mut triangles = vec![];
+    collect_triangles(&mesh.root, &mut triangles);
+    let face_normals: Vec<Vector3> = triangles.iter()
+        .map(|triangle| area_normal(mesh, triangle))
+        .collect();
+
+    let mut incident: Vec<Vec<usize>> = vec![vec![]; mesh.vertices.len()];
+    for (face, triangle) in triangles.iter().enumerate() {
+        if face_normals[face].magnitude() < EPSILON { continue }
+        for vertex in triangle.vertices.iter() {
+            incident[*vertex].push(face);
+        }
+    }
+
+    let min_cosine = crease_angle.cos();
+    let mut result = mesh.clone();
+    result.normals = vec![];
+    let mut seen: HashMap<(u64, u64, u64), usize> = HashMap::new();
+    let mut assigned: Vec<Option<[usize; 3]>> = vec![];
+
+    for (face, triangle) in triangles.iter().enumerate() {
+        let own = face_normals[face];
+        if own.magnitude() < EPSILON {
+            assigned.push(None);
+            continue;
+        }
+        let own_direction = own.normalize();
+        let mut indices = [0; 3];
+        for (corner, vertex) in triangle.vertices.iter().enumerate() {
+            let mut sum = Vector3::new(0.0, 0.0, 0.0);
+            for other in incident[*vertex].iter() {
+                let other_normal = face_normals[*other];
+                if own_direction * other_normal.normalize() >= min_cosine - EPSILON {
+                    sum = sum + other_normal;
+                }
+            }
+            let normal = sum.normalize();
+            let key = (normal.0.to_bits(), normal.1.to_bits(), normal.2.to_bits());
+            let normals = &mut result.normals;
+            indices[corner] = *seen.entry(key).or_insert_with(|| {
+                normals.push(normal);
+                normals.len() - 1
+            });
+        }
+        assigned.push(Some(indices));
+    }
+
+    let mut next = assigned.into_iter();
+    for_each_triangle_mut(&mut result.root, &mut |triangle| {
+        triangle.normals = next.next().unwrap();
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point3::Point3;
+
+    // Two right triangles meeting at a 90 degree fold along the x axis.
+    fn folded() -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.vertices.push(Point3::new(0.0, 0.0, 0.0));
+        mesh.vertices.push(Point3::new(1.0, 0.0, 0.0));
+        mesh.vertices.push(Point3::new(0.0, 1.0, 0.0));
+        mesh.vertices.push(Point3::new(0.0, 0.0, 1.0));
+        mesh.root.triangles.push(MeshTriangle::new([0, 1, 2]));
+        let mut child = MeshGroup::new("side");
+        child.triangles.push(MeshTriangle::new([1, 0, 3]));
+        mesh.root.children.push(child);
+        mesh
+    }
+
+    #[test]
+    fn a_flat_mesh_shares_one_normal() {
+        let mut mesh = Mesh::new();
+        mesh.vertices.push(Point3::new(0.0, 0.0, 0.0));
+        mesh.vertices.push(Point3::new(1.0, 0.0, 0.0));
+        mesh.vertices.push(Point3::new(1.0, 1.0, 0.0));
+        mesh.vertices.push(Point3::new(0.0, 1.0, 0.0));
+        mesh.root.triangles.push(MeshTriangle::new([0, 1, 2]));
+        mesh.root.triangles.push(MeshTriangle::new([0, 2, 3]));
+        let smooth = generate_normals(&mesh, 0.5);
+
+        assert_eq!(smooth.normals, vec![Vector3::new(0.0, 0.0, 1.0)]);
+        assert_eq!(smooth.root.triangles[0].normals, Some([0, 0, 0]));
+        assert_eq!(smooth.root.triangles[1].normals, Some([0, 0, 0]));
+    }
+
+    #[test]
+    fn faces_beyond_the_crease_angle_stay_sharp() {
+        let sharp = generate_normals(&folded(), 30.0_f64.to_radians());
+        let flat_normals = sharp.root.triangles[0].normals.unwrap();
+        let side_normals = sharp.root.children[0].triangles[0].normals.unwrap();
+
+        assert_eq!(sharp.normals[flat_normals[0]], Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(sharp.normals[side_normals[0]], Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn faces_within_the_crease_angle_are_smoothed() {
+        let smooth = generate_normals(&folded(), 100.0_f64.to_radians());
+        let flat_normals = smooth.root.triangles[0].normals.unwrap();
+        let side_normals = smooth.root.children[0].triangles[0].normals.unwrap();
+        let blended = Vector3::new(0.0, 1.0, 1.0).normalize();
+
+        // Vertices 0 and 1 sit on the fold; vertices 2 and 3 touch one face each.
+        assert_eq!(smooth.normals[flat_normals[0]], blended);
+        assert_eq!(smooth.normals[flat_normals[1]], blended);
+        assert_eq!(smooth.normals[flat_normals[2]], Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(smooth.normals[side_normals[2]], Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(flat_normals[0], side_normals[1]);
+    }
+
+    #[test]
+    fn degenerate_triangles_get_no_normals() {
+        let mut mesh = folded();
+        mesh.root.triangles.push(MeshTriangle::new([0, 1, 1]));
+        let smooth = generate_normals(&mesh, 0.5);
+
+        assert_eq!(smooth.root.triangles[1].normals, None);
+        assert!(smooth.root.triangles[0].normals.is_some());
+    }
+}
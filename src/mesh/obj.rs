@@ -0,0 +1,485 @@
+use std::fmt;
+use std::io::{self, Write};
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::mesh::{Mesh, MeshGroup, MeshTriangle};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjError {
+    pub line: usize,
+    pub message: String
+}
+
+impl ObjError {
+    fn new(line: usize, message: String) -> ObjError {
+        ObjError {
+            line,
+            message
+        }
+    }
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+#[derive(Debug, Clone)]
+pub struct ObjFile {
+    pub mesh: Mesh,
+    pub ignored_lines: Vec<usize>
+}
+
+struct FaceVertex {
+    vertex: usize,
+    texture_coord: Option<usize>,
+    normal: Option<usize>
+}
+
+// Rust parses "nan" and "inf" as numbers, but no mesh coordinate can be one.
+fn parse_float(token: &str, line: usize) -> Result<f64, ObjError> {
+    let value = token.parse::<f64>().map_err(|_| {
+        ObjError::new(line, format!("invalid number '{}'", token))
+    })?;
+    if !value.is_finite() {
+        return Err(ObjError::new(line, format!("number '{}' is not finite", token)));
+    }
+    Ok(value)
+}
+
+fn parse_floats(tokens: &[&str], min: usize, line: usize) -> Result<Vec<f64>, ObjError> {
+    if tokens.len() < min {
+        return Err(ObjError::new(
+            line,
+            format!("expected at least {} values, found {}", min, tokens.len())
+        ));
+    }
+    tokens.iter().map(|token| parse_float(token, line)).collect()
+}
+
+// OBJ indices start at 1; negative indices count back from the last element read so far.
+fn resolve_index(token: &str, count: usize, line: usize) -> Result<usize, ObjError> {
+    let index = token.parse::<i64>().map_err(|_| {
+        ObjError::new(line, format!("invalid index '{}'", token))
+    })?;
+    let resolved = if index > 0 {
+        index - 1
+    } else {
+        count as i64 + index
+    };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(ObjError::new(
+            line,
+            format!("index {} out of range ({} defined)", index, count)
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_face_vertex(token: &str, mesh: &Mesh, line: usize) -> Result<FaceVertex, ObjError> {
+    let parts: Vec<&str> = token.split('/').collect();
+    if parts.len() > 3 {
+        return Err(ObjError::new(line, format!("invalid face vertex '{}'", token)));
+    }
+    let vertex = resolve_index(parts[0], mesh.vertices.len(), line)?;
+    let texture_coord = match parts.get(1) {
+        Some(part) if !part.is_empty() => {
+            Some(resolve_index(part, mesh.texture_coords.len(), line)?)
+        },
+        _ => None
+    };
+    let normal = match parts.get(2) {
+        Some(part) if !part.is_empty() => Some(resolve_index(part, mesh.normals.len(), line)?),
+        _ => None
+    };
+    Ok(FaceVertex {
+        vertex,
+        texture_coord,
+        normal
+    })
+}
+
+// Polygons are split into a fan of triangles around their first vertex.
+fn triangulate(face: &[FaceVertex]) -> Vec<MeshTriangle> {
+    let has_texture_coords = face.iter().all(|v| v.texture_coord.is_some());
+    let has_normals = face.iter().all(|v| v.normal.is_some());
+    let mut triangles = vec![];
+    for i in 1..face.len() - 1 {
+        let corners = [&face[0], &face[i], &face[i + 1]];
+        triangles.push(MeshTriangle {
+            vertices: [corners[0].vertex, corners[1].vertex, corners[2].vertex],
+            texture_coords: if has_texture_coords {
+                Some([
+                    corners[0].texture_coord.unwrap(),
+                    corners[1].texture_coord.unwrap(),
+                    corners[2].texture_coord.unwrap()
+                ])
+            } else {
+                None
+            },
+            normals: if has_normals {
+                Some([
+                    corners[0].normal.unwrap(),
+                    corners[1].normal.unwrap(),
+                    corners[2].normal.unwrap()
+                ])
+            } else {
+                None
+            }
+        });
+    }
+    triangles
+}
+
+// Group names are paths: "g body/door" is the door group nested in body.
+fn group_at_path<'a>(root: &'a mut MeshGroup, path: &str) -> &'a mut MeshGroup {
+    let mut group = root;
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        let position = group.children.iter().position(|child| child.name == name);
+        group = match position {
+            Some(index) => &mut group.children[index],
+            None => {
+                group.children.push(MeshGroup::new(name));
+                group.children.last_mut().unwrap()
+            }
+        };
+    }
+    group
+}
+
+pub fn parse_obj(source: &str) -> Result<ObjFile, ObjError> {
+    let mut mesh = Mesh::new();
+    let mut ignored_lines = vec![];
+    let mut faces: Vec<(Option<String>, Vec<MeshTriangle>)> = vec![];
+    let mut current_group: Option<String> = None;
+
+    for (i, raw_line) in source.lines().enumerate() {
+        let line = i + 1;
+        let content = raw_line.split('#').next().unwrap_or("");
+        let tokens: Vec<&str> = content.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        match tokens[0] {
+            "v" => {
+                let values = parse_floats(&tokens[1..], 3, line)?;
+                mesh.vertices.push(Point3::new(values[0], values[1], values[2]));
+            },
+            "vn" => {
+                let values = parse_floats(&tokens[1..], 3, line)?;
+                mesh.normals.push(Vector3::new(values[0], values[1], values[2]));
+            },
+            "vt" => {
+                let values = parse_floats(&tokens[1..], 1, line)?;
+                mesh.texture_coords.push((values[0], *values.get(1).unwrap_or(&0.0)));
+            },
+            "f" => {
+                if tokens.len() < 4 {
+                    return Err(ObjError::new(
+                        line,
+                        format!("face needs at least 3 vertices, found {}", tokens.len() - 1)
+                    ));
+                }
+                let face = tokens[1..].iter()
+                    .map(|token| parse_face_vertex(token, &mesh, line))
+                    .collect::<Result<Vec<FaceVertex>, ObjError>>()?;
+                faces.push((current_group.clone(), triangulate(&face)));
+            },
+            "g" => {
+                current_group = if tokens.len() > 1 {
+                    Some(tokens[1..].join(" "))
+                } else {
+                    None
+                };
+            },
+            _ => ignored_lines.push(line)
+        }
+    }
+
+    for (group, triangles) in faces {
+        match group {
+            None => mesh.root.triangles.extend(triangles),
+            Some(path) => group_at_path(&mut mesh.root, &path).triangles.extend(triangles)
+        }
+    }
+
+    Ok(ObjFile {
+        mesh,
+        ignored_lines
+    })
+}
+
+fn write_index(
+    writer: &mut impl Write,
+    vertex: usize,
+    texture: Option<usize>,
+    normal: Option<usize>
+) -> io::Result<()> {
+    match (texture, normal) {
+        (None, None) => write!(writer, " {}", vertex + 1),
+        (Some(texture), None) => write!(writer, " {}/{}", vertex + 1, texture + 1),
+        (None, Some(normal)) => write!(writer, " {}//{}", vertex + 1, normal + 1),
+        (Some(texture), Some(normal)) => {
+            write!(writer, " {}/{}/{}", vertex + 1, texture + 1, normal + 1)
+        }
+    }
+}
+
+fn write_triangles(writer: &mut impl Write, triangles: &[MeshTriangle]) -> io::Result<()> {
+    for triangle in triangles {
+        write!(writer, "f")?;
+        for corner in 0..3 {
+            write_index(
+                writer,
+                triangle.vertices[corner],
+                triangle.texture_coords.map(|indices| indices[corner]),
+                triangle.normals.map(|indices| indices[corner])
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+// OBJ groups don't nest, so each group is written under its full path, which
+// parse_obj turns back into the hierarchy. Groups with no triangles anywhere
+// below them are not written.
+fn write_group(writer: &mut impl Write, group: &MeshGroup, parent_path: &str) -> io::Result<()> {
+    let path = if parent_path.is_empty() {
+        group.name.clone()
+    } else {
+        format!("{}/{}", parent_path, group.name)
+    };
+    if !group.triangles.is_empty() {
+        writeln!(writer, "g {}", path)?;
+        write_triangles(writer, &group.triangles)?;
+    }
+    for child in group.children.iter() {
+        write_group(writer, child, &path)?;
+    }
+    Ok(())
+}
+
+// Writes the mesh as OBJ text. Bake any transform in with Mesh::transformed
+// first; the output is plain vertex data.
+pub fn write_obj<W: Write>(mesh: &Mesh, writer: &mut W) -> io::Result<()> {
+    for vertex in mesh.vertices.iter() {
+        writeln!(writer, "v {} {} {}", vertex.0, vertex.1, vertex.2)?;
+    }
+    for (u, v) in mesh.texture_coords.iter() {
+        writeln!(writer, "vt {} {}", u, v)?;
+    }
+    for normal in mesh.normals.iter() {
+        writeln!(writer, "vn {} {} {}", normal.0, normal.1, normal.2)?;
+    }
+    write_triangles(writer, &mesh.root.triangles)?;
+    for child in mesh.root.children.iter() {
+        write_group(writer, child, "")?;
+    }
+    Ok(())
+}
+
+pub fn mesh_to_obj(mesh: &Mesh) -> String {
+    let mut buffer = vec![];
+    write_obj(mesh, &mut buffer).expect("writing to a Vec cannot fail");
+    String::from_utf8(buffer).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignoring_unrecognized_lines() {
+        let gibberish = "There was a young lady named Bright\n\
+                         who traveled much faster than light.\n\
+                         She set out one day\n\
+                         in a relative way,\n\
+                         and came back the previous night.";
+        let obj = parse_obj(gibberish).unwrap();
+
+        assert_eq!(obj.ignored_lines, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn vertex_records() {
+        let file = "v -1 1 0\nv -1.0000 0.5000 0.0000\nv 1 0 0\nv 1 1 0";
+        let obj = parse_obj(file).unwrap();
+
+        assert_eq!(obj.mesh.vertices[0], Point3::new(-1.0, 1.0, 0.0));
+        assert_eq!(obj.mesh.vertices[1], Point3::new(-1.0, 0.5, 0.0));
+        assert_eq!(obj.mesh.vertices[2], Point3::new(1.0, 0.0, 0.0));
+        assert_eq!(obj.mesh.vertices[3], Point3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn parsing_triangle_faces() {
+        let file = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\nf 1 2 3\nf 1 3 4";
+        let obj = parse_obj(file).unwrap();
+
+        assert_eq!(obj.mesh.root.triangles[0].vertices, [0, 1, 2]);
+        assert_eq!(obj.mesh.root.triangles[1].vertices, [0, 2, 3]);
+        assert!(obj.ignored_lines.is_empty());
+    }
+
+    #[test]
+    fn triangulating_polygons() {
+        let file = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nv 0 2 0\n\nf 1 2 3 4 5";
+        let obj = parse_obj(file).unwrap();
+        let triangles = &obj.mesh.root.triangles;
+
+        assert_eq!(triangles.len(), 3);
+        assert_eq!(triangles[0].vertices, [0, 1, 2]);
+        assert_eq!(triangles[1].vertices, [0, 2, 3]);
+        assert_eq!(triangles[2].vertices, [0, 3, 4]);
+    }
+
+    #[test]
+    fn triangles_in_named_groups() {
+        let file = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\
+                    g FirstGroup\nf 1 2 3\ng SecondGroup\nf 1 3 4\ng FirstGroup\nf 2 3 4";
+        let obj = parse_obj(file).unwrap();
+        let first = obj.mesh.root.child("FirstGroup").unwrap();
+        let second = obj.mesh.root.child("SecondGroup").unwrap();
+
+        assert_eq!(obj.mesh.root.children.len(), 2);
+        assert_eq!(first.triangles.len(), 2);
+        assert_eq!(first.triangles[0].vertices, [0, 1, 2]);
+        assert_eq!(second.triangles[0].vertices, [0, 2, 3]);
+        assert_eq!(obj.mesh.root.triangle_count(), 3);
+    }
+
+    #[test]
+    fn vertex_normal_records() {
+        let file = "vn 0 0 1\nvn 0.707 0 -0.707\nvn 1 2 3";
+        let obj = parse_obj(file).unwrap();
+
+        assert_eq!(obj.mesh.normals[0], Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(obj.mesh.normals[1], Vector3::new(0.707, 0.0, -0.707));
+        assert_eq!(obj.mesh.normals[2], Vector3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn faces_with_normals_and_texture_coords() {
+        let file = "v 0 1 0\nv -1 0 0\nv 1 0 0\n\
+                    vt 0 0\nvt 1 0\nvt 0.5 1\n\
+                    vn -1 0 0\nvn 1 0 0\nvn 0 1 0\n\
+                    f 1//3 2//1 3//2\nf 1/1/3 2/2/1 3/3/2\nf 1/3 2/1 3/2";
+        let obj = parse_obj(file).unwrap();
+        let triangles = &obj.mesh.root.triangles;
+
+        assert_eq!(triangles[0].normals, Some([2, 0, 1]));
+        assert_eq!(triangles[0].texture_coords, None);
+        assert_eq!(triangles[1].normals, Some([2, 0, 1]));
+        assert_eq!(triangles[1].texture_coords, Some([0, 1, 2]));
+        assert_eq!(triangles[2].normals, None);
+        assert_eq!(triangles[2].texture_coords, Some([2, 0, 1]));
+        assert_eq!(obj.mesh.texture_coords[2], (0.5, 1.0));
+    }
+
+    #[test]
+    fn negative_indices_count_from_the_end() {
+        let file = "v 0 1 0\nv -1 0 0\nv 1 0 0\nf -3 -2 -1";
+        let obj = parse_obj(file).unwrap();
+
+        assert_eq!(obj.mesh.root.triangles[0].vertices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn comments_are_not_reported_as_ignored() {
+        let file = "# a comment\nv 0 1 0 # trailing\no object\n";
+        let obj = parse_obj(file).unwrap();
+
+        assert_eq!(obj.mesh.vertices.len(), 1);
+        assert_eq!(obj.ignored_lines, vec![3]);
+    }
+
+    #[test]
+    fn malformed_numbers_report_their_line() {
+        let file = "v 0 1 0\nv 1 zero 0";
+        let err = parse_obj(file).unwrap_err();
+
+        assert_eq!(err.line, 2);
+        assert_eq!(err.to_string(), "line 2: invalid number 'zero'");
+    }
+
+    #[test]
+    fn non_finite_numbers_are_rejected() {
+        let err = parse_obj("v 0 1 0\nv 1 0 0\nv nan 0 0").unwrap_err();
+
+        assert_eq!(err.to_string(), "line 3: number 'nan' is not finite");
+        assert_eq!(parse_obj("vn 0 inf 0").unwrap_err().line, 1);
+        assert_eq!(parse_obj("vt -infinity 0").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn group_paths_nest() {
+        let file = "v 0 1 0\nv -1 0 0\nv 1 0 0\n\
+                    g car/wheels/front\nf 1 2 3\ng car\nf 3 2 1\ng car/wheels/back\nf 1 3 2";
+        let root = parse_obj(file).unwrap().mesh.root;
+        let car = &root.children[0];
+        let wheels = &car.children[0];
+
+        assert_eq!(root.children.len(), 1);
+        assert_eq!((car.name.as_str(), car.triangles.len()), ("car", 1));
+        assert!(wheels.triangles.is_empty());
+        assert_eq!(wheels.children.iter().map(|g| g.name.as_str()).collect::<Vec<_>>(), vec!["front", "back"]);
+    }
+
+    #[test]
+    fn out_of_range_face_indices_are_errors() {
+        let file = "v 0 1 0\nv -1 0 0\nv 1 0 0\nf 1 2 4";
+        let err = parse_obj(file).unwrap_err();
+
+        assert_eq!(err.line, 4);
+        assert_eq!(parse_obj("v 0 0 0\nf 0 1 1").unwrap_err().line, 2);
+    }
+
+    #[test]
+    fn writing_a_mesh_as_obj() {
+        let file = "v 0 1 0\nv -1 0 0\nv 1 0 0\nv 0 0 1\nvn 0 0 1\n\
+                    f 1 2 3\ng Lid\nf 1//1 3//1 4//1";
+        let mesh = parse_obj(file).unwrap().mesh;
+        let expected = "v 0 1 0\nv -1 0 0\nv 1 0 0\nv 0 0 1\nvn 0 0 1\n\
+                        f 1 2 3\ng Lid\nf 1//1 3//1 4//1\n";
+
+        assert_eq!(mesh_to_obj(&mesh), expected);
+    }
+
+    #[test]
+    fn round_tripping_a_mesh_through_obj() {
+        let file = "v -1 1 0\nv -1.5 0.25 0\nv 1 0 0\nv 1 1 0\nv 0 2 0\n\
+                    vt 0 0\nvt 1 0\nvt 0.5 1\nvn 0 0.6 0.8\n\
+                    f 1 2 3 4 5\ng Front\nf 1/1/1 2/2/1 3/3/1\ng Back\nf 3/1 4/2 5/3";
+        let original = parse_obj(file).unwrap().mesh;
+        let reloaded = parse_obj(&mesh_to_obj(&original)).unwrap().mesh;
+
+        assert_eq!(reloaded.vertices, original.vertices);
+        assert_eq!(reloaded.texture_coords, original.texture_coords);
+        assert_eq!(reloaded.normals, original.normals);
+        assert_eq!(reloaded.root, original.root);
+    }
+
+    #[test]
+    fn nested_groups_survive_a_round_trip() {
+        let file = "v 0 1 0\nv -1 0 0\nv 1 0 0\nf 1 2 3\n\
+                    g body\nf 1 2 3\ng body/door\nf 3 2 1\ng body/door/handle\nf 2 1 3\ng wheel\nf 1 3 2";
+        let original = parse_obj(file).unwrap().mesh;
+        let obj = mesh_to_obj(&original);
+
+        assert!(obj.contains("g body/door/handle\n"));
+        assert_eq!(parse_obj(&obj).unwrap().mesh.root, original.root);
+        assert_eq!(original.root.children[0].children[0].children[0].name, "handle");
+    }
+
+    #[test]
+    fn faces_need_three_vertices() {
+        let file = "v 0 1 0\nv -1 0 0\nf 1 2";
+
+        assert_eq!(parse_obj(file).unwrap_err().line, 3);
+    }
+}
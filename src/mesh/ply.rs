@@ -0,0 +1,551 @@
+use std::convert::TryInto;
+use std::fmt;
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::mesh::{Mesh, MeshTriangle};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyError {
+    pub message: String
+}
+
+impl PlyError {
+    fn new(message: String) -> PlyError {
+        PlyError {
+            message
+        }
+    }
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid PLY: {}", self.message)
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<ScalarType, PlyError> {
+        match name {
+            "char" | "int8" => Ok(ScalarType::Int8),
+            "uchar" | "uint8" => Ok(ScalarType::UInt8),
+            "short" | "int16" => Ok(ScalarType::Int16),
+            "ushort" | "uint16" => Ok(ScalarType::UInt16),
+            "int" | "int32" => Ok(ScalarType::Int32),
+            "uint" | "uint32" => Ok(ScalarType::UInt32),
+            "float" | "float32" => Ok(ScalarType::Float32),
+            "double" | "float64" => Ok(ScalarType::Float64),
+            _ => Err(PlyError::new(format!("unknown property type '{}'", name)))
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar {
+        name: String,
+        kind: ScalarType
+    },
+    List {
+        name: String,
+        count_kind: ScalarType,
+        item_kind: ScalarType
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>
+}
+
+fn parse_header(text: &str) -> Result<Header, PlyError> {
+    let mut lines = text.lines();
+    if lines.next().map(|line| line.trim()) != Some("ply") {
+        return Err(PlyError::new(String::from("missing 'ply' magic number")));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            Some(&"format") => {
+                format = match tokens.get(1) {
+                    Some(&"ascii") => Some(Format::Ascii),
+                    Some(&"binary_little_endian") => Some(Format::BinaryLittleEndian),
+                    Some(other) => {
+                        return Err(PlyError::new(format!("unsupported format '{}'", other)));
+                    },
+                    None => return Err(PlyError::new(String::from("format line has no format")))
+                };
+            },
+            Some(&"element") => {
+                if tokens.len() != 3 {
+                    return Err(PlyError::new(format!("malformed element line '{}'", line)));
+                }
+                let count = tokens[2].parse::<usize>().map_err(|_| {
+                    PlyError::new(format!("invalid element count '{}'", tokens[2]))
+                })?;
+                elements.push(Element {
+                    name: String::from(tokens[1]),
+                    count,
+                    properties: vec![]
+                });
+            },
+            Some(&"property") => {
+                let element = elements.last_mut().ok_or_else(|| {
+                    PlyError::new(String::from("property declared before any element"))
+                })?;
+                let property = if tokens.get(1) == Some(&"list") && tokens.len() == 5 {
+                    Property::List {
+                        name: String::from(tokens[4]),
+                        count_kind: ScalarType::parse(tokens[2])?,
+                        item_kind: ScalarType::parse(tokens[3])?
+                    }
+                } else if tokens.len() == 3 {
+                    Property::Scalar {
+                        name: String::from(tokens[2]),
+                        kind: ScalarType::parse(tokens[1])?
+                    }
+                } else {
+                    return Err(PlyError::new(format!("malformed property line '{}'", line)));
+                };
+                element.properties.push(property);
+            },
+            Some(&"comment") | Some(&"obj_info") | None => {},
+            Some(other) => {
+                return Err(PlyError::new(format!("unknown header keyword '{}'", other)));
+            }
+        }
+    }
+
+    match format {
+        Some(format) => Ok(Header {
+            format,
+            elements
+        }),
+        None => Err(PlyError::new(String::from("header has no format line")))
+    }
+}
+
+enum BodyReader<'a> {
+    Ascii(&'a str),
+    Binary(&'a [u8])
+}
+
+impl<'a> BodyReader<'a> {
+    fn read(&mut self, kind: ScalarType) -> Result<f64, PlyError> {
+        match self {
+            BodyReader::Ascii(text) => {
+                let rest = text.trim_start();
+                if rest.is_empty() {
+                    return Err(PlyError::new(String::from("unexpected end of data")));
+                }
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let (token, after) = rest.split_at(end);
+                *text = after;
+                token.parse::<f64>().map_err(|_| {
+                    PlyError::new(format!("invalid number '{}'", token))
+                })
+            },
+            BodyReader::Binary(data) => {
+                let size = kind.size();
+                if data.len() < size {
+                    return Err(PlyError::new(String::from("unexpected end of data")));
+                }
+                let (bytes, rest) = data.split_at(size);
+                *data = rest;
+                Ok(match kind {
+                    ScalarType::Int8 => bytes[0] as i8 as f64,
+                    ScalarType::UInt8 => bytes[0] as f64,
+                    ScalarType::Int16 => i16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::UInt16 => u16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::Int32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::UInt32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::Float32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::Float64 => f64::from_le_bytes(bytes.try_into().unwrap())
+                })
+            }
+        }
+    }
+
+    // The most values of this kind the rest of the body could hold; ASCII
+    // values need at least a digit and a separator each.
+    fn max_remaining(&self, kind: ScalarType) -> usize {
+        match self {
+            BodyReader::Ascii(text) => text.len().div_ceil(2),
+            BodyReader::Binary(data) => data.len() / kind.size()
+        }
+    }
+
+    // A list length, checked against what is left so a corrupt count fails
+    // here instead of after reading the rest of the file.
+    fn read_count(&mut self, count_kind: ScalarType, item_kind: ScalarType) -> Result<usize, PlyError> {
+        let value = self.read(count_kind)?;
+        let count = to_index(value).ok_or_else(|| {
+            PlyError::new(format!("invalid list length {}", value))
+        })?;
+        if count > self.max_remaining(item_kind) {
+            return Err(PlyError::new(format!("list of {} items runs past the end of the data", count)));
+        }
+        Ok(count)
+    }
+}
+
+// Counts and indices must be whole and non-negative.
+fn to_index(value: f64) -> Option<usize> {
+    if value >= 0.0 && value.fract() == 0.0 && value <= usize::MAX as f64 {
+        Some(value as usize)
+    } else {
+        None
+    }
+}
+
+fn find_header_end(data: &[u8]) -> Option<(usize, usize)> {
+    let marker = b"end_header";
+    let start = data.windows(marker.len()).position(|window| window == marker)?;
+    let mut body = start + marker.len();
+    if data.get(body) == Some(&b'\r') {
+        body += 1;
+    }
+    if data.get(body) == Some(&b'\n') {
+        body += 1;
+    }
+    Some((start, body))
+}
+
+#[derive(Default)]
+struct VertexLayout {
+    position: [Option<usize>; 3],
+    normal: [Option<usize>; 3],
+    texture: [Option<usize>; 2]
+}
+
+impl VertexLayout {
+    fn from_element(element: &Element) -> VertexLayout {
+        let mut layout = VertexLayout::default();
+        for (i, property) in element.properties.iter().enumerate() {
+            if let Property::Scalar { name, .. } = property {
+                match name.as_str() {
+                    "x" => layout.position[0] = Some(i),
+                    "y" => layout.position[1] = Some(i),
+                    "z" => layout.position[2] = Some(i),
+                    "nx" => layout.normal[0] = Some(i),
+                    "ny" => layout.normal[1] = Some(i),
+                    "nz" => layout.normal[2] = Some(i),
+                    "u" | "s" | "texture_u" => layout.texture[0] = Some(i),
+                    "v" | "t" | "texture_v" => layout.texture[1] = Some(i),
+                    _ => {}
+                }
+            }
+        }
+        layout
+    }
+
+    fn has_normals(&self) -> bool {
+        self.normal.iter().all(|index| index.is_some())
+    }
+
+    fn has_texture_coords(&self) -> bool {
+        self.texture.iter().all(|index| index.is_some())
+    }
+}
+
+pub fn parse_ply(data: &[u8]) -> Result<Mesh, PlyError> {
+    let (header_end, body_start) = find_header_end(data).ok_or_else(|| {
+        PlyError::new(String::from("missing end_header"))
+    })?;
+    let header_text = std::str::from_utf8(&data[..header_end]).map_err(|_| {
+        PlyError::new(String::from("header contains invalid UTF-8"))
+    })?;
+    let header = parse_header(header_text)?;
+    let body = &data[body_start..];
+    let mut reader = match header.format {
+        Format::Ascii => {
+            let text = std::str::from_utf8(body).map_err(|_| {
+                PlyError::new(String::from("ASCII body contains invalid UTF-8"))
+            })?;
+            BodyReader::Ascii(text)
+        },
+        Format::BinaryLittleEndian => BodyReader::Binary(body)
+    };
+
+    let mut mesh = Mesh::new();
+    let mut has_normals = false;
+    let mut has_texture_coords = false;
+    let mut faces: Vec<Vec<usize>> = vec![];
+
+    for element in header.elements.iter() {
+        // An element without properties takes up no data however large its
+        // count, so there is nothing to read.
+        if element.properties.is_empty() && element.name != "vertex" && element.name != "face" {
+            continue;
+        }
+        let layout = VertexLayout::from_element(element);
+        if element.name == "vertex" {
+            if layout.position.iter().any(|index| index.is_none()) {
+                return Err(PlyError::new(String::from("vertex element lacks x, y or z")));
+            }
+            has_normals = layout.has_normals();
+            has_texture_coords = layout.has_texture_coords();
+        }
+
+        for _ in 0..element.count {
+            let mut scalars = vec![0.0; element.properties.len()];
+            let mut face = None;
+            for (i, property) in element.properties.iter().enumerate() {
+                match property {
+                    Property::Scalar { kind, .. } => scalars[i] = reader.read(*kind)?,
+                    Property::List { name, count_kind, item_kind } => {
+                        let count = reader.read_count(*count_kind, *item_kind)?;
+                        let mut items = vec![];
+                        for _ in 0..count {
+                            items.push(reader.read(*item_kind)?);
+                        }
+                        if name == "vertex_indices" || name == "vertex_index" {
+                            face = Some(items);
+                        }
+                    }
+                }
+            }
+
+            let value = |index: Option<usize>| scalars[index.unwrap()];
+            if element.name == "vertex" {
+                mesh.vertices.push(Point3::new(
+                    value(layout.position[0]),
+                    value(layout.position[1]),
+                    value(layout.position[2])
+                ));
+                if has_normals {
+                    mesh.normals.push(Vector3::new(
+                        value(layout.normal[0]),
+                        value(layout.normal[1]),
+                        value(layout.normal[2])
+                    ));
+                }
+                if has_texture_coords {
+                    mesh.texture_coords.push((value(layout.texture[0]), value(layout.texture[1])));
+                }
+            } else if element.name == "face" {
+                let indices = face.ok_or_else(|| {
+                    PlyError::new(String::from("face element lacks vertex_indices"))
+                })?;
+                let face = indices.into_iter()
+                    .map(|index| to_index(index).ok_or_else(|| {
+                        PlyError::new(format!("invalid vertex index {}", index))
+                    }))
+                    .collect::<Result<Vec<usize>, PlyError>>()?;
+                faces.push(face);
+            }
+        }
+    }
+
+    for (number, face) in faces.iter().enumerate() {
+        if face.len() < 3 {
+            return Err(PlyError::new(format!("face {} has fewer than 3 vertices", number)));
+        }
+        if let Some(index) = face.iter().find(|index| **index >= mesh.vertices.len()) {
+            return Err(PlyError::new(format!(
+                "face {} uses vertex {} but only {} are defined",
+                number, index, mesh.vertices.len()
+            )));
+        }
+        for i in 1..face.len() - 1 {
+            let corners = [face[0], face[i], face[i + 1]];
+            mesh.root.triangles.push(MeshTriangle {
+                vertices: corners,
+                texture_coords: if has_texture_coords { Some(corners) } else { None },
+                normals: if has_normals { Some(corners) } else { None }
+            });
+        }
+    }
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII_QUAD: &str = "ply\n\
+        format ascii 1.0\n\
+        comment a unit square\n\
+        element vertex 4\n\
+        property float x\n\
+        property float y\n\
+        property float z\n\
+        element face 1\n\
+        property list uchar int vertex_indices\n\
+        end_header\n\
+        0 0 0\n1 0 0\n1 1 0\n0 1 0\n\
+        4 0 1 2 3\n";
+
+    #[test]
+    fn reading_an_ascii_ply() {
+        let mesh = parse_ply(ASCII_QUAD.as_bytes()).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.vertices[2], Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.root.triangles.len(), 2);
+        assert_eq!(mesh.root.triangles[0].vertices, [0, 1, 2]);
+        assert_eq!(mesh.root.triangles[1].vertices, [0, 2, 3]);
+        assert_eq!(mesh.root.triangles[0].normals, None);
+    }
+
+    #[test]
+    fn ply_normals_make_smooth_triangles() {
+        let source = "ply\nformat ascii 1.0\n\
+            element vertex 3\n\
+            property double x\nproperty double y\nproperty double z\n\
+            property float nx\nproperty float ny\nproperty float nz\n\
+            property float s\nproperty float t\n\
+            element face 1\nproperty list uchar uint vertex_index\n\
+            end_header\n\
+            0 1 0 0 1 0 0.5 1\n-1 0 0 -1 0 0 0 0\n1 0 0 1 0 0 1 0\n\
+            3 0 1 2\n";
+        let mesh = parse_ply(source.as_bytes()).unwrap();
+        let triangle = mesh.root.triangles[0];
+
+        assert_eq!(triangle.normals, Some([0, 1, 2]));
+        assert_eq!(triangle.texture_coords, Some([0, 1, 2]));
+        assert_eq!(mesh.normals[1], Vector3::new(-1.0, 0.0, 0.0));
+        assert_eq!(mesh.texture_coords[0], (0.5, 1.0));
+    }
+
+    #[test]
+    fn reading_a_binary_little_endian_ply() {
+        let mut data = b"ply\r\nformat binary_little_endian 1.0\r\n\
+            element vertex 3\r\nproperty float x\r\nproperty float y\r\nproperty float z\r\n\
+            element edge 1\r\nproperty int vertex1\r\nproperty int vertex2\r\n\
+            element face 1\r\nproperty list uchar int vertex_indices\r\nproperty uchar flags\r\n\
+            end_header\r\n".to_vec();
+        for value in [0.0f32, 1.0, 0.0, -1.0, 0.0, 0.0, 1.0, 0.0, 0.5].iter() {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&0i32.to_le_bytes());
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.push(3);
+        for index in [2i32, 0, 1].iter() {
+            data.extend_from_slice(&index.to_le_bytes());
+        }
+        data.push(7);
+        let mesh = parse_ply(&data).unwrap();
+
+        assert_eq!(mesh.vertices[2], Point3::new(1.0, 0.0, 0.5));
+        assert_eq!(mesh.root.triangles[0].vertices, [2, 0, 1]);
+    }
+
+    #[test]
+    fn big_endian_files_are_rejected() {
+        let source = "ply\nformat binary_big_endian 1.0\nend_header\n";
+
+        assert_eq!(
+            parse_ply(source.as_bytes()).unwrap_err().to_string(),
+            "invalid PLY: unsupported format 'binary_big_endian'"
+        );
+    }
+
+    #[test]
+    fn malformed_headers_are_errors() {
+        assert_eq!(
+            parse_ply(b"plx\nformat ascii 1.0\nend_header\n").unwrap_err().message,
+            "missing 'ply' magic number"
+        );
+        assert_eq!(parse_ply(b"ply\nformat ascii 1.0\n").unwrap_err().message, "missing end_header");
+        assert_eq!(
+            parse_ply(b"ply\nformat ascii 1.0\nproperty float x\nend_header\n").unwrap_err().message,
+            "property declared before any element"
+        );
+    }
+
+    #[test]
+    fn truncated_data_and_bad_indices_are_errors() {
+        let truncated = ASCII_QUAD.replace("4 0 1 2 3\n", "4 0 1 2");
+        let truncated_vertices = ASCII_QUAD.replace("0 1 0\n4 0 1 2 3\n", "0 1");
+        let out_of_range = ASCII_QUAD.replace("4 0 1 2 3", "4 0 1 2 9");
+
+        assert_eq!(
+            parse_ply(truncated.as_bytes()).unwrap_err().message,
+            "list of 4 items runs past the end of the data"
+        );
+        assert_eq!(parse_ply(truncated_vertices.as_bytes()).unwrap_err().message, "unexpected end of data");
+        assert_eq!(
+            parse_ply(out_of_range.as_bytes()).unwrap_err().message,
+            "face 0 uses vertex 9 but only 4 are defined"
+        );
+    }
+
+    #[test]
+    fn negative_and_fractional_indices_are_errors() {
+        let negative = ASCII_QUAD.replace("4 0 1 2 3", "4 0 1 2 -1");
+        let fractional = ASCII_QUAD.replace("4 0 1 2 3", "4 0 1 2 2.5");
+
+        assert_eq!(parse_ply(negative.as_bytes()).unwrap_err().message, "invalid vertex index -1");
+        assert_eq!(parse_ply(fractional.as_bytes()).unwrap_err().message, "invalid vertex index 2.5");
+    }
+
+    #[test]
+    fn elements_without_properties_are_skipped() {
+        let source = ASCII_QUAD.replace("element face 1", "element empty 18446744073709551615\nelement face 1");
+        let empty_face = "ply\nformat ascii 1.0\nelement face 18446744073709551615\nend_header\n";
+
+        assert_eq!(parse_ply(source.as_bytes()).unwrap().root.triangles.len(), 2);
+        assert_eq!(parse_ply(empty_face.as_bytes()).unwrap_err().message, "face element lacks vertex_indices");
+    }
+
+    #[test]
+    fn list_lengths_are_checked_against_the_data() {
+        let huge = ASCII_QUAD.replace("4 0 1 2 3", "4000000000 0 1 2 3");
+        let negative = ASCII_QUAD.replace("4 0 1 2 3", "-4 0 1 2 3");
+
+        assert_eq!(
+            parse_ply(huge.as_bytes()).unwrap_err().message,
+            "list of 4000000000 items runs past the end of the data"
+        );
+        assert_eq!(parse_ply(negative.as_bytes()).unwrap_err().message, "invalid list length -4");
+
+        let mut binary = b"ply\nformat binary_little_endian 1.0\nelement vertex 0\nproperty float x\n\
+            property float y\nproperty float z\nelement face 1\n\
+            property list uchar int vertex_indices\nend_header\n".to_vec();
+        binary.push(200);
+        binary.extend_from_slice(&[0; 12]);
+        assert_eq!(
+            parse_ply(&binary).unwrap_err().message,
+            "list of 200 items runs past the end of the data"
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use crate::point3::Point3;
+use crate::mesh::{Mesh, MeshTriangle};
+
+const HEADER_SIZE: usize = 84;
+const FACET_SIZE: usize = 50;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StlError {
+    pub message: String
+}
+
+impl StlError {
+    fn new(message: String) -> StlError {
+        StlError {
+            message
+        }
+    }
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid STL: {}", self.message)
+    }
+}
+
+impl std::error::Error for StlError {}
+
+// STL repeats every corner for every facet; welding identical positions back
+// together gives triangles shared vertices to work with.
+struct Welder {
+    mesh: Mesh,
+    seen: HashMap<(u64, u64, u64), usize>
+}
+
+impl Welder {
+    fn new() -> Welder {
+        Welder {
+            mesh: Mesh::new(),
+            seen: HashMap::new()
+        }
+    }
+
+    fn vertex(&mut self, point: Point3) -> usize {
+        let key = (point.0.to_bits(), point.1.to_bits(), point.2.to_bits());
+        let vertices = &mut self.mesh.vertices;
+        *self.seen.entry(key).or_insert_with(|| {
+            vertices.push(point);
+            vertices.len() - 1
+        })
+    }
+
+    fn triangle(&mut self, corners: [Point3; 3]) {
+        let indices = [
+            self.vertex(corners[0]),
+            self.vertex(corners[1]),
+            self.vertex(corners[2])
+        ];
+        self.mesh.root.triangles.push(MeshTriangle::new(indices));
+    }
+}
+
+fn read_f32(data: &[u8], offset: usize) -> f64 {
+    f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as f64
+}
+
+fn parse_binary(data: &[u8]) -> Result<Mesh, StlError> {
+    if data.len() < HEADER_SIZE {
+        return Err(StlError::new(format!("file is only {} bytes", data.len())));
+    }
+    let count = u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize;
+    let expected = HEADER_SIZE + count * FACET_SIZE;
+    if data.len() < expected {
+        return Err(StlError::new(format!(
+            "header declares {} facets ({} bytes) but file is {} bytes",
+            count, expected, data.len()
+        )));
+    }
+
+    let mut welder = Welder::new();
+    for facet in 0..count {
+        // Each facet is a normal, three corners and a two byte attribute.
+        let start = HEADER_SIZE + facet * FACET_SIZE + 12;
+        let mut corners = [Point3::new(0.0, 0.0, 0.0); 3];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let offset = start + i * 12;
+            *corner = Point3::new(
+                read_f32(data, offset),
+                read_f32(data, offset + 4),
+                read_f32(data, offset + 8)
+            );
+        }
+        welder.triangle(corners);
+    }
+    Ok(welder.mesh)
+}
+
+fn parse_ascii(source: &str) -> Result<Mesh, StlError> {
+    let mut welder = Welder::new();
+    let mut corners: Vec<Point3> = vec![];
+    let mut tokens = source.split_whitespace();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "vertex" => {
+                let mut coordinates = [0.0; 3];
+                for coordinate in coordinates.iter_mut() {
+                    let text = tokens.next().ok_or_else(|| {
+                        StlError::new(String::from("vertex is missing coordinates"))
+                    })?;
+                    *coordinate = text.parse::<f64>().map_err(|_| {
+                        StlError::new(format!("invalid number '{}'", text))
+                    })?;
+                }
+                corners.push(Point3::new(coordinates[0], coordinates[1], coordinates[2]));
+            },
+            "endloop" => {
+                if corners.len() < 3 {
+                    return Err(StlError::new(format!(
+                        "facet has {} vertices, expected 3", corners.len()
+                    )));
+                }
+                // Some exporters write polygons; fan them like OBJ faces.
+                for i in 1..corners.len() - 1 {
+                    welder.triangle([corners[0], corners[i], corners[i + 1]]);
+                }
+                corners.clear();
+            },
+            _ => {}
+        }
+    }
+    Ok(welder.mesh)
+}
+
+// Binary files may also begin with "solid", so the size implied by the facet
+// count decides first, allowing for padding some exporters leave at the end.
+// Anything else starting with "solid", after any blank space, is read as text.
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() >= HEADER_SIZE {
+        let count = u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize;
+        if data.len() >= HEADER_SIZE + count * FACET_SIZE {
+            return true;
+        }
+    }
+    let start = data.iter().position(|byte| !byte.is_ascii_whitespace()).unwrap_or(data.len());
+    !data[start..].starts_with(b"solid")
+}
+
+pub fn parse_stl(data: &[u8]) -> Result<Mesh, StlError> {
+    if is_binary(data) {
+        return parse_binary(data);
+    }
+    let source = std::str::from_utf8(data).map_err(|_| {
+        StlError::new(String::from("ASCII STL contains invalid UTF-8"))
+    })?;
+    parse_ascii(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary_stl(facets: &[[[f32; 3]; 3]]) -> Vec<u8> {
+        let mut data = vec![0u8; 80];
+        data.extend_from_slice(&(facets.len() as u32).to_le_bytes());
+        for facet in facets {
+            data.extend_from_slice(&[0u8; 12]);
+            for corner in facet {
+                for value in corner {
+                    data.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            data.extend_from_slice(&[0u8; 2]);
+        }
+        data
+    }
+
+    #[test]
+    fn reading_an_ascii_stl() {
+        let source = "solid square\n\
+            facet normal 0 0 1\n outer loop\n  vertex 0 0 0\n  vertex 1 0 0\n  vertex 1 1 0\n endloop\nendfacet\n\
+            facet normal 0 0 1\n outer loop\n  vertex 0 0 0\n  vertex 1 1 0\n  vertex 0 1 0\n endloop\nendfacet\n\
+            endsolid square\n";
+        let mesh = parse_stl(source.as_bytes()).unwrap();
+
+        assert_eq!(mesh.root.triangles.len(), 2);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.root.triangles[0].vertices, [0, 1, 2]);
+        assert_eq!(mesh.root.triangles[1].vertices, [0, 2, 3]);
+        assert_eq!(mesh.vertices[3], Point3::new(0.0, 1.0, 0.0));
+        assert_eq!(mesh.root.triangles[0].normals, None);
+    }
+
+    #[test]
+    fn reading_a_binary_stl() {
+        let data = binary_stl(&[
+            [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            [[1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]
+        ]);
+        let mesh = parse_stl(&data).unwrap();
+
+        assert_eq!(mesh.root.triangles.len(), 2);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.root.triangles[1].vertices, [1, 3, 2]);
+        assert_eq!(mesh.vertices[3], Point3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn a_binary_stl_whose_header_starts_with_solid() {
+        let mut data = binary_stl(&[[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]]);
+        data[..5].copy_from_slice(b"solid");
+
+        assert_eq!(parse_stl(&data).unwrap().root.triangles.len(), 1);
+    }
+
+    #[test]
+    fn a_padded_binary_stl_is_still_binary() {
+        let mut data = binary_stl(&[[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]]);
+        data[..5].copy_from_slice(b"solid");
+        data.extend_from_slice(&[0u8; 16]);
+
+        assert_eq!(parse_stl(&data).unwrap().root.triangles.len(), 1);
+    }
+
+    #[test]
+    fn an_ascii_stl_may_start_with_blank_space() {
+        let source = "\n  solid tri\nfacet normal 0 0 1\nouter loop\n\
+            vertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid tri\n";
+
+        assert_eq!(parse_stl(source.as_bytes()).unwrap().root.triangles.len(), 1);
+    }
+
+    #[test]
+    fn a_truncated_binary_stl_is_an_error() {
+        let mut data = binary_stl(&[[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]]);
+        data.truncate(100);
+        let err = parse_stl(&data).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "invalid STL: header declares 1 facets (134 bytes) but file is 100 bytes"
+        );
+    }
+
+    #[test]
+    fn malformed_ascii_vertices_are_errors() {
+        let source = "solid bad\nfacet normal 0 0 1\nouter loop\nvertex 0 zero 0\nendloop\nendfacet\nendsolid bad";
+
+        assert_eq!(parse_stl(source.as_bytes()).unwrap_err().message, "invalid number 'zero'");
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::mesh::{Mesh, MeshGroup, MeshTriangle};
+use crate::mesh::normals::collect_triangles;
+
+fn edge(a: usize, b: usize) -> (usize, usize) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+fn offset(point: Point3) -> Vector3 {
+    point - Point3::new(0.0, 0.0, 0.0)
+}
+
+struct EdgeInfo {
+    opposite: Vec<usize>,
+    midpoint: usize
+}
+
+struct Subdivider<'a> {
+    mesh: &'a Mesh,
+    edges: HashMap<(usize, usize), EdgeInfo>,
+    uv_midpoints: HashMap<(usize, usize), usize>,
+    result: Mesh
+}
+
+impl<'a> Subdivider<'a> {
+    fn new(mesh: &'a Mesh) -> Subdivider<'a> {
+        let mut triangles = vec![];
+        collect_triangles(&mesh.root, &mut triangles);
+        let mut edges: HashMap<(usize, usize), EdgeInfo> = HashMap::new();
+        for triangle in triangles.iter() {
+            let v = triangle.vertices;
+            for i in 0..3 {
+                edges.entry(edge(v[i], v[(i + 1) % 3]))
+                    .or_insert_with(|| EdgeInfo { opposite: vec![], midpoint: 0 })
+                    .opposite
+                    .push(v[(i + 2) % 3]);
+            }
+        }
+        Subdivider {
+            mesh,
+            edges,
+            uv_midpoints: HashMap::new(),
+            result: Mesh::new()
+        }
+    }
+
+    // Edges with exactly two faces are smooth; anything else (an open
+    // boundary or a non-manifold edge) is treated as a crease.
+    fn is_boundary(info: &EdgeInfo) -> bool {
+        info.opposite.len() != 2
+    }
+
+    fn reposition_vertices(&mut self) {
+        let vertex_count = self.mesh.vertices.len();
+        let mut neighbours: Vec<HashSet<usize>> = vec![HashSet::new(); vertex_count];
+        let mut boundary_neighbours: Vec<Vec<usize>> = vec![vec![]; vertex_count];
+        for ((a, b), info) in self.edges.iter() {
+            neighbours[*a].insert(*b);
+            neighbours[*b].insert(*a);
+            if Subdivider::is_boundary(info) {
+                boundary_neighbours[*a].push(*b);
+                boundary_neighbours[*b].push(*a);
+            }
+        }
+
+        for (index, vertex) in self.mesh.vertices.iter().enumerate() {
+            let v = offset(*vertex);
+            let moved = if boundary_neighbours[index].len() == 2 {
+                let b = &boundary_neighbours[index];
+                v * 0.75 + (offset(self.mesh.vertices[b[0]]) + offset(self.mesh.vertices[b[1]])) * 0.125
+            } else if !boundary_neighbours[index].is_empty() || neighbours[index].is_empty() {
+                // Corners where several boundaries meet, and unused vertices, stay put.
+                v
+            } else {
+                let n = neighbours[index].len() as f64;
+                let beta = if neighbours[index].len() == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * n) };
+                let sum = neighbours[index].iter()
+                    .fold(Vector3::new(0.0, 0.0, 0.0), |acc, other| acc + offset(self.mesh.vertices[*other]));
+                v * (1.0 - n * beta) + sum * beta
+            };
+            self.result.vertices.push(Point3::new(0.0, 0.0, 0.0) + moved);
+        }
+    }
+
+    fn add_edge_vertices(&mut self) {
+        let mut keys: Vec<(usize, usize)> = self.edges.keys().cloned().collect();
+        keys.sort_unstable();
+        for key in keys {
+            let info = self.edges.get_mut(&key).unwrap();
+            let a = offset(self.mesh.vertices[key.0]);
+            let b = offset(self.mesh.vertices[key.1]);
+            let position = if Subdivider::is_boundary(info) {
+                (a + b) * 0.5
+            } else {
+                let c = offset(self.mesh.vertices[info.opposite[0]]);
+                let d = offset(self.mesh.vertices[info.opposite[1]]);
+                (a + b) * 0.375 + (c + d) * 0.125
+            };
+            info.midpoint = self.result.vertices.len();
+            self.result.vertices.push(Point3::new(0.0, 0.0, 0.0) + position);
+        }
+    }
+
+    // Texture coordinates are interpolated linearly so seams stay put.
+    fn uv_midpoint(&mut self, a: usize, b: usize) -> usize {
+        let texture_coords = &mut self.result.texture_coords;
+        *self.uv_midpoints.entry(edge(a, b)).or_insert_with(|| {
+            let (ua, va) = texture_coords[a];
+            let (ub, vb) = texture_coords[b];
+            texture_coords.push(((ua + ub) / 2.0, (va + vb) / 2.0));
+            texture_coords.len() - 1
+        })
+    }
+
+    fn split(&mut self, triangle: &MeshTriangle) -> [MeshTriangle; 4] {
+        let v = triangle.vertices;
+        let m = [
+            self.edges[&edge(v[0], v[1])].midpoint,
+            self.edges[&edge(v[1], v[2])].midpoint,
+            self.edges[&edge(v[2], v[0])].midpoint
+        ];
+        let corners = |p: [usize; 3], q: [usize; 3]| [
+            [p[0], q[0], q[2]],
+            [q[0], p[1], q[1]],
+            [q[2], q[1], p[2]],
+            [q[0], q[1], q[2]]
+        ];
+        let vertex_sets = corners(v, m);
+        let uv_sets = triangle.texture_coords.map(|t| {
+            let mid = [
+                self.uv_midpoint(t[0], t[1]),
+                self.uv_midpoint(t[1], t[2]),
+                self.uv_midpoint(t[2], t[0])
+            ];
+            corners(t, mid)
+        });
+        let mut pieces = [MeshTriangle::new([0; 3]); 4];
+        for (i, piece) in pieces.iter_mut().enumerate() {
+            piece.vertices = vertex_sets[i];
+            piece.texture_coords = uv_sets.map(|sets| sets[i]);
+        }
+        pieces
+    }
+
+    fn split_group(&mut self, group: &MeshGroup) -> MeshGroup {
+        let mut split = MeshGroup::new(&group.name);
+        for triangle in group.triangles.iter() {
+            split.triangles.extend_from_slice(&self.split(triangle));
+        }
+        for child in group.children.iter() {
+            let child = self.split_group(child);
+            split.children.push(child);
+        }
+        split
+    }
+}
+
+// One level of Loop subdivision: every triangle becomes four and vertices are
+// smoothed towards their neighbours, keeping open boundaries in place along
+// their own curve. Groups are preserved. Normals are dropped because they no
+// longer match the surface; regenerate them with generate_normals.
+fn subdivide_once(mesh: &Mesh) -> Mesh {
+    let mut subdivider = Subdivider::new(mesh);
+    subdivider.result.texture_coords = mesh.texture_coords.clone();
+    subdivider.reposition_vertices();
+    subdivider.add_edge_vertices();
+    let root = subdivider.split_group(&mesh.root);
+    let mut result = subdivider.result;
+    result.root = root;
+    result
+}
+
+pub fn loop_subdivide(mesh: &Mesh, levels: usize) -> Mesh {
+    let mut result = mesh.clone();
+    for _ in 0..levels {
+        result = subdivide_once(&result);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single_triangle() -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.vertices.push(Point3::new(0.0, 0.0, 0.0));
+        mesh.vertices.push(Point3::new(4.0, 0.0, 0.0));
+        mesh.vertices.push(Point3::new(0.0, 4.0, 0.0));
+        mesh.texture_coords.push((0.0, 0.0));
+        mesh.texture_coords.push((1.0, 0.0));
+        mesh.texture_coords.push((0.0, 1.0));
+        let mut triangle = MeshTriangle::new([0, 1, 2]);
+        triangle.texture_coords = Some([0, 1, 2]);
+        mesh.root.triangles.push(triangle);
+        mesh
+    }
+
+    // A regular tetrahedron centred on the origin.
+    fn tetrahedron() -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.vertices.push(Point3::new(1.0, 1.0, 1.0));
+        mesh.vertices.push(Point3::new(1.0, -1.0, -1.0));
+        mesh.vertices.push(Point3::new(-1.0, 1.0, -1.0));
+        mesh.vertices.push(Point3::new(-1.0, -1.0, 1.0));
+        mesh.root.triangles.push(MeshTriangle::new([0, 1, 2]));
+        mesh.root.triangles.push(MeshTriangle::new([0, 3, 1]));
+        let mut back = MeshGroup::new("back");
+        back.triangles.push(MeshTriangle::new([0, 2, 3]));
+        back.triangles.push(MeshTriangle::new([1, 3, 2]));
+        mesh.root.children.push(back);
+        mesh
+    }
+
+    #[test]
+    fn subdividing_zero_levels_changes_nothing() {
+        let mesh = tetrahedron();
+        let same = loop_subdivide(&mesh, 0);
+
+        assert_eq!(same.vertices, mesh.vertices);
+        assert_eq!(same.root, mesh.root);
+    }
+
+    #[test]
+    fn a_lone_triangle_splits_into_four() {
+        let result = loop_subdivide(&single_triangle(), 1);
+
+        assert_eq!(result.vertices.len(), 6);
+        assert_eq!(result.root.triangles.len(), 4);
+        // Every edge is open: corners follow the boundary rule and midpoints sit halfway.
+        assert_eq!(result.vertices[0], Point3::new(0.5, 0.5, 0.0));
+        assert!(result.vertices.contains(&Point3::new(2.0, 0.0, 0.0)));
+        assert!(result.vertices.contains(&Point3::new(2.0, 2.0, 0.0)));
+        assert!(result.vertices.contains(&Point3::new(0.0, 2.0, 0.0)));
+    }
+
+    #[test]
+    fn texture_coordinates_are_interpolated() {
+        let result = loop_subdivide(&single_triangle(), 1);
+        let centre = result.root.triangles[3];
+        let uvs = centre.texture_coords.unwrap();
+
+        assert_eq!(result.texture_coords.len(), 6);
+        assert_eq!(result.texture_coords[uvs[0]], (0.5, 0.0));
+        assert_eq!(result.texture_coords[uvs[1]], (0.5, 0.5));
+        assert_eq!(result.texture_coords[uvs[2]], (0.0, 0.5));
+    }
+
+    #[test]
+    fn subdividing_a_closed_mesh_smooths_it() {
+        let result = loop_subdivide(&tetrahedron(), 1);
+
+        assert_eq!(result.vertices.len(), 10);
+        assert_eq!(result.root.triangle_count(), 16);
+        assert_eq!(result.root.child("back").unwrap().triangles.len(), 8);
+        // Original corners pull in to a quarter of their distance; edge points
+        // land at a quarter of the sum of their endpoints.
+        assert_eq!(result.vertices[0], Point3::new(0.25, 0.25, 0.25));
+        assert!(result.vertices.contains(&Point3::new(0.5, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn repeated_subdivision_quadruples_the_faces() {
+        let result = loop_subdivide(&tetrahedron(), 3);
+
+        assert_eq!(result.root.triangle_count(), 4 * 64);
+        // Euler characteristic of a closed surface: V - E + F = 2, with E = 3F / 2.
+        assert_eq!(result.vertices.len(), 2 + 4 * 64 / 2);
+    }
+
+    #[test]
+    fn subdivision_drops_stale_normals() {
+        let mut mesh = tetrahedron();
+        mesh.normals.push(Vector3::new(0.0, 1.0, 0.0));
+
+        assert!(loop_subdivide(&mesh, 1).normals.is_empty());
+    }
+}
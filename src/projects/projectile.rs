@@ -1,7 +1,9 @@
 use std::{fs,env};
+use std::io::BufWriter;
 use crate::point3::*;
 use crate::vector3::*;
 use crate::canvas::*;
+use crate::canvas::ppm::{PpmFormat, BitDepth};
 use crate::color::*;
 
 pub struct Projectile {
@@ -56,17 +58,22 @@ pub fn log_projectile() {
 
     while proj.position.1 > 0.0 {
         proj = tick(env, proj);
-        canvas.write_pixel(
+        let y = match (canvas.height() - 1).checked_sub(proj.position.1 as usize) {
+            Some(y) => y,
+            None => continue
+        };
+        if let Err(err) = canvas.try_write_pixel(
             proj.position.0 as usize,
-            199 - proj.position.1 as usize,
+            y,
             Color::new(1.0, 1.0, 1.0)
-        );
+        ) {
+            println!("Skipping point: {}", err);
+        }
     }
 
-    let ppm = canvas.canvas_to_ppm();
-
     let mut path = env::current_dir().unwrap();
     path.push("eye_candy/projectile.ppm");
-    fs::write(path, ppm).expect("Unable to write file");
+    let mut file = BufWriter::new(fs::File::create(path).expect("Unable to create file"));
+    canvas.write_ppm(&mut file, PpmFormat::PlainRgb, BitDepth::Eight, OutputEncoding::default()).expect("Unable to write file");
 
 }
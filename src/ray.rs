@@ -0,0 +1,81 @@
+use crate::point3::*;
+use crate::vector3::*;
+use crate::matrix::matrix4::Matrix4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray {
+    pub origin: Point3,
+    pub direction: Vector3
+}
+
+impl Ray {
+    pub fn new(origin: Point3, direction: Vector3) -> Ray {
+        Ray {
+            origin,
+            direction
+        }
+    }
+
+    pub fn position(&self, t: f64) -> Point3 {
+        self.origin + self.direction * t
+    }
+
+    pub fn transform(&self, matrix: Matrix4) -> Ray {
+        Ray::new(matrix * self.origin, matrix * self.direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creating_and_querying_a_ray() {
+        let origin = Point3::new(1.0, 2.0, 3.0);
+        let direction = Vector3::new(4.0, 5.0, 6.0);
+        let r = Ray::new(origin, direction);
+
+        assert_eq!(r.origin, origin);
+        assert_eq!(r.direction, direction);
+    }
+
+    #[test]
+    fn computing_a_point_from_a_distance() {
+        let r = Ray::new(Point3::new(2.0, 3.0, 4.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(r.position(0.0), Point3::new(2.0, 3.0, 4.0));
+        assert_eq!(r.position(1.0), Point3::new(3.0, 3.0, 4.0));
+        assert_eq!(r.position(-1.0), Point3::new(1.0, 3.0, 4.0));
+        assert_eq!(r.position(2.5), Point3::new(4.5, 3.0, 4.0));
+    }
+
+    #[test]
+    fn translating_a_ray() {
+        let r = Ray::new(Point3::new(1.0, 2.0, 3.0), Vector3::new(0.0, 1.0, 0.0));
+        let m = Matrix4::new([
+            1.0, 0.0, 0.0, 3.0,
+            0.0, 1.0, 0.0, 4.0,
+            0.0, 0.0, 1.0, 5.0,
+            0.0, 0.0, 0.0, 1.0
+        ]);
+        let r2 = r.transform(m);
+
+        assert_eq!(r2.origin, Point3::new(4.0, 6.0, 8.0));
+        assert_eq!(r2.direction, Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn scaling_a_ray() {
+        let r = Ray::new(Point3::new(1.0, 2.0, 3.0), Vector3::new(0.0, 1.0, 0.0));
+        let m = Matrix4::new([
+            2.0, 0.0, 0.0, 0.0,
+            0.0, 3.0, 0.0, 0.0,
+            0.0, 0.0, 4.0, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ]);
+        let r2 = r.transform(m);
+
+        assert_eq!(r2.origin, Point3::new(2.0, 6.0, 12.0));
+        assert_eq!(r2.direction, Vector3::new(0.0, 3.0, 0.0));
+    }
+}
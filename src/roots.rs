@@ -0,0 +1,188 @@
+const EPSILON: f64 = 1e-12;
+const POLISH_ITERATIONS: usize = 8;
+
+fn sort_roots(mut roots: Vec<f64>) -> Vec<f64> {
+    roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    roots
+}
+
+// Real roots of a*x^2 + b*x + c, in ascending order.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < EPSILON {
+        if b.abs() < EPSILON {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    if discriminant == 0.0 {
+        return vec![-b / (2.0 * a)];
+    }
+    // Avoids cancellation between -b and the square root.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let q = if q == 0.0 { -0.5 * discriminant.sqrt() } else { q };
+    sort_roots(vec![q / a, c / q])
+}
+
+// Real roots of a*x^3 + b*x^2 + c*x + d, in ascending order.
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a.abs() < EPSILON {
+        return solve_quadratic(b, c, d);
+    }
+    let (b, c, d) = (b / a, c / a, d / a);
+
+    // Substituting x = y - b/3 gives the depressed cubic y^3 + p*y + q.
+    let shift = b / 3.0;
+    let p = c - b * b / 3.0;
+    let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+    let discriminant = q * q / 4.0 + p * p * p / 27.0;
+
+    let roots = if p.abs() < EPSILON && q.abs() < EPSILON {
+        vec![0.0]
+    } else if discriminant > EPSILON {
+        let sqrt_disc = discriminant.sqrt();
+        vec![(-q / 2.0 + sqrt_disc).cbrt() + (-q / 2.0 - sqrt_disc).cbrt()]
+    } else if discriminant < -EPSILON {
+        let r = 2.0 * (-p / 3.0).sqrt();
+        let phi = ((3.0 * q) / (p * r)).clamp(-1.0, 1.0).acos() / 3.0;
+        let third = 2.0 * std::f64::consts::PI / 3.0;
+        vec![r * phi.cos(), r * (phi - third).cos(), r * (phi - 2.0 * third).cos()]
+    } else {
+        let u = (-q / 2.0).cbrt();
+        vec![2.0 * u, -u]
+    };
+
+    sort_roots(roots.into_iter().map(|y| y - shift).collect())
+}
+
+fn evaluate(coefficients: &[f64; 5], x: f64) -> (f64, f64) {
+    let mut value = 0.0;
+    let mut derivative = 0.0;
+    for coefficient in coefficients.iter() {
+        derivative = derivative * x + value;
+        value = value * x + coefficient;
+    }
+    (value, derivative)
+}
+
+// Newton iterations against the original polynomial clean up the error the
+// closed-form solution picks up along the way.
+fn polish(coefficients: &[f64; 5], root: f64) -> f64 {
+    let mut x = root;
+    for _ in 0..POLISH_ITERATIONS {
+        let (value, derivative) = evaluate(coefficients, x);
+        if derivative.abs() < EPSILON {
+            break;
+        }
+        let next = x - value / derivative;
+        if !next.is_finite() {
+            break;
+        }
+        x = next;
+    }
+    x
+}
+
+// Real roots of a*x^4 + b*x^3 + c*x^2 + d*x + e, in ascending order, solved
+// with Ferrari's method and then polished with Newton's method.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a.abs() < EPSILON {
+        return solve_cubic(b, c, d, e);
+    }
+    let coefficients = [1.0, b / a, c / a, d / a, e / a];
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+
+    // Substituting x = y - b/4 gives the depressed quartic y^4 + p*y^2 + q*y + r.
+    let shift = b / 4.0;
+    let p = c - 3.0 * b * b / 8.0;
+    let q = d - b * c / 2.0 + b * b * b / 8.0;
+    let r = e - b * d / 4.0 + b * b * c / 16.0 - 3.0 * b * b * b * b / 256.0;
+
+    let mut roots = vec![];
+    if q.abs() < EPSILON {
+        // Biquadratic: solve for y^2.
+        for square in solve_quadratic(1.0, p, r) {
+            if square > EPSILON {
+                roots.push(square.sqrt());
+                roots.push(-square.sqrt());
+            } else if square > -EPSILON {
+                roots.push(0.0);
+            }
+        }
+    } else {
+        // Any positive root z of the resolvent cubic factors the quartic into
+        // two quadratics; the largest one is the best conditioned.
+        let z = solve_cubic(1.0, 2.0 * p, p * p - 4.0 * r, -q * q)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if z <= 0.0 {
+            return vec![];
+        }
+        let s = z.sqrt();
+        roots.extend(solve_quadratic(1.0, s, (p + z - q / s) / 2.0));
+        roots.extend(solve_quadratic(1.0, -s, (p + z + q / s) / 2.0));
+    }
+
+    sort_roots(roots.into_iter().map(|y| polish(&coefficients, y - shift)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx_equal::approx_equal::equal;
+
+    fn assert_roots(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "roots: {:?}", actual);
+        for (root, want) in actual.iter().zip(expected.iter()) {
+            assert!(equal(*root, *want), "roots: {:?}", actual);
+        }
+    }
+
+    #[test]
+    fn solving_quadratics() {
+        assert_roots(solve_quadratic(1.0, -3.0, 2.0), &[1.0, 2.0]);
+        assert_roots(solve_quadratic(1.0, 0.0, 1.0), &[]);
+        assert_roots(solve_quadratic(1.0, -2.0, 1.0), &[1.0]);
+        assert_roots(solve_quadratic(0.0, 2.0, -4.0), &[2.0]);
+    }
+
+    #[test]
+    fn solving_cubics() {
+        // (x - 1)(x - 2)(x - 3)
+        assert_roots(solve_cubic(1.0, -6.0, 11.0, -6.0), &[1.0, 2.0, 3.0]);
+        // (x - 2)(x^2 + 1)
+        assert_roots(solve_cubic(1.0, -2.0, 1.0, -2.0), &[2.0]);
+        // (x - 1)^2 (x + 2)
+        assert_roots(solve_cubic(1.0, 0.0, -3.0, 2.0), &[-2.0, 1.0]);
+        assert_roots(solve_cubic(2.0, 0.0, 0.0, 0.0), &[0.0]);
+    }
+
+    #[test]
+    fn solving_quartics_with_four_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0), &[1.0, 2.0, 3.0, 4.0]);
+        // 2(x + 0.5)(x - 0.25)(x - 7)(x + 3)
+        assert_roots(
+            solve_quartic(2.0, -7.5, -44.25, -9.5, 5.25),
+            &[-3.0, -0.5, 0.25, 7.0]
+        );
+    }
+
+    #[test]
+    fn solving_quartics_with_two_or_no_roots() {
+        // (x - 1)(x + 1)(x^2 + 1)
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, -1.0), &[-1.0, 1.0]);
+        // (x - 2)(x - 5)(x^2 + x + 1)
+        assert_roots(solve_quartic(1.0, -6.0, 4.0, 3.0, 10.0), &[2.0, 5.0]);
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0), &[]);
+    }
+
+    #[test]
+    fn solving_biquadratic_quartics() {
+        // (x^2 - 1)(x^2 - 4)
+        assert_roots(solve_quartic(1.0, 0.0, -5.0, 0.0, 4.0), &[-2.0, -1.0, 1.0, 2.0]);
+    }
+}
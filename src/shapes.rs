@@ -0,0 +1,91 @@
+pub mod torus;
+pub mod disk;
+pub mod rectangle;
+pub mod sdf;
+pub mod metaballs;
+pub mod height_field;
+pub mod instance;
+pub mod triangle;
+pub mod sphere;
+pub mod group;
+pub mod csg;
+pub mod triangle_mesh;
+
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::intersection::Intersection;
+
+// Shapes work in their own object space: rays and points arrive already
+// transformed into it, and the returned normal is in object space too.
+// Shapes are immutable once built, so they can be shared between render
+// threads.
+pub trait Shape: AsShape + Send + Sync {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64>;
+
+    fn local_normal_at(&self, point: Point3) -> Vector3;
+
+    fn bounds(&self) -> BoundingBox;
+
+    // Full hits rather than bare t values. Shapes with children, or with
+    // surface coordinates to report, override this.
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.local_intersect(ray).into_iter()
+            .map(|t| Intersection::new(t, self.as_shape()))
+            .collect()
+    }
+
+    // The normal for a particular hit. Only shapes whose normal depends on
+    // more than the point, such as smooth triangles, need to override it.
+    fn local_normal_at_hit(&self, point: Point3, _hit: &Intersection) -> Vector3 {
+        self.local_normal_at(point)
+    }
+}
+
+// Lets the provided Shape methods hand out self as a trait object.
+pub trait AsShape {
+    fn as_shape(&self) -> &dyn Shape;
+}
+
+impl<T: Shape> AsShape for T {
+    fn as_shape(&self) -> &dyn Shape {
+        self
+    }
+}
+
+// The normal at a point for shapes built from other shapes, where which part
+// the point lies on is only known from a hit. Short rays are cast through the
+// point; the hit nearest to it supplies the normal, taken through the hit so
+// nested transforms and smooth triangles are honoured. A point nowhere near
+// the surface gets the direction away from the middle of the shape.
+pub fn normal_through_hits(shape: &dyn Shape, point: Point3) -> Vector3 {
+    let bounds = shape.bounds();
+    let size = if bounds.is_finite() && !bounds.is_empty() {
+        (bounds.max - bounds.min).magnitude()
+    } else {
+        1.0
+    };
+    let offset = size * 0.001;
+    let probes = [
+        Vector3::new(1.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(1.0, 1.0, 1.0).normalize()
+    ];
+    let mut nearest: Option<(f64, Vector3)> = None;
+    for direction in probes.iter() {
+        let probe = Ray::new(point - *direction * offset, *direction);
+        for hit in shape.intersect(&probe) {
+            let distance = (hit.t - offset).abs();
+            if nearest.is_none_or(|(best, _)| distance < best) {
+                nearest = Some((distance, hit.normal_at(point)));
+            }
+        }
+    }
+    match nearest {
+        Some((_, normal)) => normal,
+        None if bounds.is_finite() && !bounds.is_empty() => (point - bounds.centroid()).normalize(),
+        None => Vector3::new(0.0, 1.0, 0.0)
+    }
+}
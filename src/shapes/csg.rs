@@ -0,0 +1,282 @@
+use super::{Shape, normal_through_hits};
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::intersection::Intersection;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CsgHit {
+    pub t: f64,
+    pub left: bool
+}
+
+impl CsgHit {
+    pub fn new(t: f64, left: bool) -> CsgHit {
+        CsgHit {
+            t,
+            left
+        }
+    }
+}
+
+// lhit: the hit is on the left operand; inl/inr: whether the hit point is
+// currently inside the left/right operand.
+pub fn intersection_allowed(op: CsgOperation, lhit: bool, inl: bool, inr: bool) -> bool {
+    match op {
+        CsgOperation::Union => (lhit && !inr) || (!lhit && !inl),
+        CsgOperation::Intersection => (lhit && inr) || (!lhit && inl),
+        CsgOperation::Difference => (lhit && !inr) || (!lhit && inl)
+    }
+}
+
+// Walks the combined hits of both operands in t order, tracking which operand
+// the ray is inside, and keeps only those on the surface of the result.
+pub fn filter_intersections(op: CsgOperation, hits: &[CsgHit]) -> Vec<CsgHit> {
+    let tagged = hits.iter().map(|hit| (*hit, hit.t, hit.left)).collect();
+    filter_tagged(op, tagged)
+}
+
+// Each entry is (hit, t, whether it is on the left operand). A NaN t sorts
+// last rather than panicking.
+fn filter_tagged<T>(op: CsgOperation, mut hits: Vec<(T, f64, bool)>) -> Vec<T> {
+    hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let mut inl = false;
+    let mut inr = false;
+    let mut result = vec![];
+    for (hit, _, left) in hits {
+        if intersection_allowed(op, left, inl, inr) {
+            result.push(hit);
+        }
+        if left {
+            inl = !inl;
+        } else {
+            inr = !inr;
+        }
+    }
+    result
+}
+
+// Two solids combined into one. Place either operand with an Instance or a
+// Group; the Csg itself has no transform.
+pub struct Csg {
+    pub operation: CsgOperation,
+    left: Box<dyn Shape>,
+    right: Box<dyn Shape>
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: Box<dyn Shape>, right: Box<dyn Shape>) -> Csg {
+        Csg {
+            operation,
+            left,
+            right
+        }
+    }
+
+    pub fn left(&self) -> &dyn Shape {
+        self.left.as_ref()
+    }
+
+    pub fn right(&self) -> &dyn Shape {
+        self.right.as_ref()
+    }
+}
+
+impl Shape for Csg {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        self.intersect(ray).into_iter().map(|hit| hit.t).collect()
+    }
+
+    // As with groups, the surface a point lies on is only known from a hit.
+    fn local_normal_at(&self, point: Point3) -> Vector3 {
+        normal_through_hits(self, point)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.left.bounds().union(&self.right.bounds())
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if !self.bounds().intersects(ray) {
+            return vec![];
+        }
+        let left = self.left.intersect(ray).into_iter().map(|hit| (hit, hit.t, true));
+        let right = self.right.intersect(ray).into_iter().map(|hit| (hit, hit.t, false));
+        filter_tagged(self.operation, left.chain(right).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::shapes::sphere::Sphere;
+    use crate::shapes::instance::Instance;
+    use crate::matrix::matrix4::Matrix4;
+
+    #[test]
+    fn evaluating_the_rule_for_a_csg_operation() {
+        use CsgOperation::*;
+        let cases = [
+            (Union, true, true, true, false),
+            (Union, true, true, false, true),
+            (Union, true, false, true, false),
+            (Union, true, false, false, true),
+            (Union, false, true, true, false),
+            (Union, false, true, false, false),
+            (Union, false, false, true, true),
+            (Union, false, false, false, true),
+            (Intersection, true, true, true, true),
+            (Intersection, true, true, false, false),
+            (Intersection, true, false, true, true),
+            (Intersection, true, false, false, false),
+            (Intersection, false, true, true, true),
+            (Intersection, false, true, false, true),
+            (Intersection, false, false, true, false),
+            (Intersection, false, false, false, false),
+            (Difference, true, true, true, false),
+            (Difference, true, true, false, true),
+            (Difference, true, false, true, false),
+            (Difference, true, false, false, true),
+            (Difference, false, true, true, true),
+            (Difference, false, true, false, true),
+            (Difference, false, false, true, false),
+            (Difference, false, false, false, false)
+        ];
+
+        for (op, lhit, inl, inr, expected) in cases.iter() {
+            assert_eq!(intersection_allowed(*op, *lhit, *inl, *inr), *expected);
+        }
+    }
+
+    #[test]
+    fn filtering_a_list_of_intersections() {
+        let hits = [
+            CsgHit::new(1.0, true),
+            CsgHit::new(2.0, false),
+            CsgHit::new(3.0, true),
+            CsgHit::new(4.0, false)
+        ];
+        let cases = [
+            (CsgOperation::Union, 1.0, 4.0),
+            (CsgOperation::Intersection, 2.0, 3.0),
+            (CsgOperation::Difference, 1.0, 2.0)
+        ];
+
+        for (op, first, second) in cases.iter() {
+            let result = filter_intersections(*op, &hits);
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].t, *first);
+            assert_eq!(result[1].t, *second);
+        }
+    }
+
+    #[test]
+    fn filtering_sorts_hits_by_distance() {
+        let hits = [
+            CsgHit::new(4.0, false),
+            CsgHit::new(3.0, true),
+            CsgHit::new(1.0, true),
+            CsgHit::new(2.0, false)
+        ];
+        let result = filter_intersections(CsgOperation::Union, &hits);
+
+        assert_eq!(result, vec![CsgHit::new(1.0, true), CsgHit::new(4.0, false)]);
+    }
+
+    #[test]
+    fn a_hole_drilled_through_a_solid() {
+        // A slab from 0 to 10 with a cylinder-like hole spanning 4 to 6.
+        let hits = [
+            CsgHit::new(0.0, true),
+            CsgHit::new(10.0, true),
+            CsgHit::new(4.0, false),
+            CsgHit::new(6.0, false)
+        ];
+        let result = filter_intersections(CsgOperation::Difference, &hits);
+        let ts: Vec<f64> = result.iter().map(|hit| hit.t).collect();
+
+        assert_eq!(ts, vec![0.0, 4.0, 6.0, 10.0]);
+    }
+
+    #[test]
+    fn a_nan_distance_does_not_stop_filtering() {
+        let hits = [
+            CsgHit::new(f64::NAN, false),
+            CsgHit::new(3.0, true),
+            CsgHit::new(1.0, true)
+        ];
+        let result = filter_intersections(CsgOperation::Union, &hits);
+
+        assert_eq!(result[..2], [CsgHit::new(1.0, true), CsgHit::new(3.0, true)]);
+    }
+
+    fn translation(x: f64, y: f64, z: f64) -> Matrix4 {
+        Matrix4::new([
+            1.0, 0.0, 0.0, x,
+            0.0, 1.0, 0.0, y,
+            0.0, 0.0, 1.0, z,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    fn two_spheres(op: CsgOperation, offset: Matrix4) -> Csg {
+        let moved = Instance::new(Arc::new(Sphere::new()), offset).unwrap();
+        Csg::new(op, Box::new(Sphere::new()), Box::new(moved))
+    }
+
+    fn hit_ts(csg: &Csg, ray: &Ray) -> Vec<f64> {
+        csg.intersect(ray).iter().map(|hit| hit.t).collect()
+    }
+
+    #[test]
+    fn combining_two_overlapping_spheres() {
+        let offset = translation(0.0, 0.0, 0.5);
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(hit_ts(&two_spheres(CsgOperation::Union, offset), &r), vec![4.0, 6.5]);
+        assert_eq!(hit_ts(&two_spheres(CsgOperation::Intersection, offset), &r), vec![4.5, 6.0]);
+        assert_eq!(hit_ts(&two_spheres(CsgOperation::Difference, offset), &r), vec![4.0, 4.5]);
+    }
+
+    #[test]
+    fn a_difference_keeps_the_carved_surface_of_the_right_operand() {
+        let csg = two_spheres(CsgOperation::Difference, translation(0.5, 0.0, 0.0));
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = csg.intersect(&r);
+        let depth = 0.75_f64.sqrt();
+
+        assert_eq!(hits.len(), 4);
+        assert!((hits[1].t - (5.0 - depth)).abs() < 1e-9);
+        assert_eq!(hits[0].normal_at(r.position(hits[0].t)), Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(hits[1].normal_at(r.position(hits[1].t)), Vector3::new(-0.5, 0.0, -depth));
+        assert_eq!(csg.local_normal_at(r.position(hits[1].t)), Vector3::new(-0.5, 0.0, -depth));
+    }
+
+    #[test]
+    fn a_ray_missing_both_operands_misses_the_csg() {
+        let csg = two_spheres(CsgOperation::Union, translation(0.0, 0.0, 0.5));
+        let r = Ray::new(Point3::new(0.0, 2.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(csg.intersect(&r).is_empty());
+        assert!(csg.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn csg_bounds_cover_both_operands() {
+        let csg = two_spheres(CsgOperation::Difference, translation(3.0, 0.0, 0.0));
+        let b = csg.bounds();
+
+        assert_eq!(b.min, Point3::new(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Point3::new(4.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,236 @@
+use std::fmt;
+use std::f64::consts::PI;
+use super::Shape;
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::intersection::Intersection;
+
+const EPSILON: f64 = 0.00001;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskError {
+    pub message: String
+}
+
+impl DiskError {
+    fn new(message: String) -> DiskError {
+        DiskError {
+            message
+        }
+    }
+}
+
+impl fmt::Display for DiskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid disk: {}", self.message)
+    }
+}
+
+impl std::error::Error for DiskError {}
+
+// A flat disk in the xz plane facing +y. A non-zero inner radius cuts a
+// hole in the middle, making it an annulus. Hits carry uv_at's coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Disk {
+    radius: f64,
+    inner_radius: f64
+}
+
+impl Disk {
+    pub fn new(radius: f64) -> Disk {
+        Disk {
+            radius,
+            inner_radius: 0.0
+        }
+    }
+
+    // The ring has to have some width, or v would divide by zero.
+    pub fn annulus(radius: f64, inner_radius: f64) -> Result<Disk, DiskError> {
+        if !(inner_radius >= 0.0 && inner_radius < radius && radius.is_finite()) {
+            return Err(DiskError::new(format!(
+                "inner radius {} must be at least 0 and less than the radius {}",
+                inner_radius, radius
+            )));
+        }
+        Ok(Disk {
+            radius,
+            inner_radius
+        })
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    pub fn inner_radius(&self) -> f64 {
+        self.inner_radius
+    }
+
+    // u runs once around the disk from +x towards +z, v from the inner edge
+    // to the outer edge.
+    pub fn uv_at(&self, point: Point3) -> (f64, f64) {
+        let theta = point.2.atan2(point.0);
+        let u = if theta < 0.0 { theta / (2.0 * PI) + 1.0 } else { theta / (2.0 * PI) };
+        let distance = (point.0 * point.0 + point.2 * point.2).sqrt();
+        let v = (distance - self.inner_radius) / (self.radius - self.inner_radius);
+        (u, v)
+    }
+
+    pub fn area(&self) -> f64 {
+        PI * (self.radius * self.radius - self.inner_radius * self.inner_radius)
+    }
+
+    // Maps a sample from the unit square to a point spread evenly over the
+    // surface, for using the disk as a light emitter.
+    pub fn sample_point(&self, u: f64, v: f64) -> Point3 {
+        let inner_squared = self.inner_radius * self.inner_radius;
+        let outer_squared = self.radius * self.radius;
+        let distance = (inner_squared + v * (outer_squared - inner_squared)).sqrt();
+        let theta = 2.0 * PI * u;
+        Point3::new(distance * theta.cos(), 0.0, distance * theta.sin())
+    }
+
+    // t and the point of the hit, if any.
+    fn hit(&self, ray: &Ray) -> Option<(f64, Point3)> {
+        if ray.direction.1.abs() < EPSILON {
+            return None;
+        }
+        let t = -ray.origin.1 / ray.direction.1;
+        let point = ray.position(t);
+        let distance_squared = point.0 * point.0 + point.2 * point.2;
+        if distance_squared > self.radius * self.radius ||
+            distance_squared < self.inner_radius * self.inner_radius {
+            return None;
+        }
+        Some((t, point))
+    }
+}
+
+impl Shape for Disk {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        self.hit(ray).map(|(t, _)| t).into_iter().collect()
+    }
+
+    fn local_normal_at(&self, _point: Point3) -> Vector3 {
+        Vector3::new(0.0, 1.0, 0.0)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point3::new(-self.radius, 0.0, -self.radius),
+            Point3::new(self.radius, 0.0, self.radius)
+        )
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.hit(ray).map(|(t, point)| {
+            let (u, v) = self.uv_at(point);
+            Intersection::with_uv(t, self, u, v)
+        }).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_ray_hits_a_disk_from_above() {
+        let d = Disk::new(1.0);
+        let r = Ray::new(Point3::new(0.5, 2.0, 0.5), Vector3::new(0.0, -1.0, 0.0));
+
+        assert_eq!(d.local_intersect(&r), vec![2.0]);
+    }
+
+    #[test]
+    fn a_ray_misses_outside_the_radius() {
+        let d = Disk::new(1.0);
+        let r = Ray::new(Point3::new(1.0, 2.0, 1.0), Vector3::new(0.0, -1.0, 0.0));
+
+        assert!(d.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn a_ray_parallel_to_a_disk_misses() {
+        let d = Disk::new(1.0);
+        let r = Ray::new(Point3::new(-2.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert!(d.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn a_ray_through_the_hole_of_an_annulus_misses() {
+        let d = Disk::annulus(2.0, 1.0).unwrap();
+        let through_hole = Ray::new(Point3::new(0.5, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let on_ring = Ray::new(Point3::new(1.5, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+
+        assert!(d.local_intersect(&through_hole).is_empty());
+        assert_eq!(d.local_intersect(&on_ring), vec![1.0]);
+    }
+
+    #[test]
+    fn hits_carry_uv_coordinates() {
+        let d = Disk::annulus(2.0, 1.0).unwrap();
+        let r = Ray::new(Point3::new(0.0, 1.0, 1.5), Vector3::new(0.0, -1.0, 0.0));
+        let hits = d.intersect(&r);
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].t, hits[0].u, hits[0].v), (1.0, 0.25, 0.5));
+    }
+
+    #[test]
+    fn an_annulus_needs_a_ring_of_some_width() {
+        assert_eq!(
+            Disk::annulus(1.0, 1.0).unwrap_err().to_string(),
+            "invalid disk: inner radius 1 must be at least 0 and less than the radius 1"
+        );
+        assert!(Disk::annulus(1.0, 2.0).is_err());
+        assert!(Disk::annulus(1.0, -0.5).is_err());
+        assert!(Disk::annulus(1.0, f64::NAN).is_err());
+        assert!(Disk::annulus(f64::INFINITY, 1.0).is_err());
+    }
+
+    #[test]
+    fn the_normal_of_a_disk_is_constant() {
+        let d = Disk::new(1.0);
+
+        assert_eq!(d.local_normal_at(Point3::new(0.3, 0.0, -0.2)), Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn uv_coordinates_on_an_annulus() {
+        let d = Disk::annulus(2.0, 1.0).unwrap();
+        let (u, v) = d.uv_at(Point3::new(1.0, 0.0, 0.0));
+        assert_eq!((u, v), (0.0, 0.0));
+
+        let (u, v) = d.uv_at(Point3::new(0.0, 0.0, 2.0));
+        assert_eq!((u, v), (0.25, 1.0));
+
+        let (u, v) = d.uv_at(Point3::new(0.0, 0.0, -1.5));
+        assert_eq!((u, v), (0.75, 0.5));
+    }
+
+    #[test]
+    fn sampled_points_lie_on_the_surface() {
+        let d = Disk::annulus(2.0, 1.0).unwrap();
+        for i in 0..10 {
+            for j in 0..10 {
+                let p = d.sample_point(i as f64 / 10.0, j as f64 / 9.0);
+                let distance = (p.0 * p.0 + p.2 * p.2).sqrt();
+                assert!((1.0 - EPSILON..=2.0 + EPSILON).contains(&distance));
+                assert_eq!(p.1, 0.0);
+            }
+        }
+        assert!((d.area() - 3.0 * PI).abs() < EPSILON);
+    }
+
+    #[test]
+    fn a_disk_has_flat_bounds() {
+        let b = Disk::new(3.0).bounds();
+
+        assert_eq!(b.min, Point3::new(-3.0, 0.0, -3.0));
+        assert_eq!(b.max, Point3::new(3.0, 0.0, 3.0));
+    }
+}
@@ -0,0 +1,315 @@
+use std::sync::OnceLock;
+use super::{Shape, normal_through_hits};
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::intersection::Intersection;
+use crate::matrix::matrix4::Matrix4;
+use crate::bvh::Bvh;
+
+// A compound shape: children are placed in the group's space and the group's
+// own transform moves them all as one. Groups nest, so hits are carried back
+// up through each level's transform in turn. A BVH over the children's bounds
+// means a ray only tests the children whose boxes it passes through, so a
+// group can hold a whole scene.
+pub struct Group {
+    children: Vec<Box<dyn Shape>>,
+    transform: Matrix4,
+    inverse: Matrix4,
+    // Union of the children's bounds, in the group's own space.
+    child_bounds: BoundingBox,
+    // Built by the first ray after the children last changed.
+    bvh: OnceLock<Bvh>
+}
+
+impl Group {
+    pub fn new() -> Group {
+        Group {
+            children: vec![],
+            transform: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+            child_bounds: BoundingBox::empty(),
+            bvh: OnceLock::new()
+        }
+    }
+
+    // None when the transform can't be inverted, e.g. a zero scale.
+    pub fn with_transform(transform: Matrix4) -> Option<Group> {
+        let inverse = transform.inverse()?;
+        Some(Group {
+            transform,
+            inverse,
+            ..Group::new()
+        })
+    }
+
+    pub fn add_child(&mut self, child: Box<dyn Shape>) {
+        self.child_bounds = self.child_bounds.union(&child.bounds());
+        self.children.push(child);
+        self.bvh = OnceLock::new();
+    }
+
+    pub fn children(&self) -> &[Box<dyn Shape>] {
+        &self.children
+    }
+
+    pub fn transform(&self) -> Matrix4 {
+        self.transform
+    }
+
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| {
+            let bounds: Vec<BoundingBox> = self.children.iter().map(|child| child.bounds()).collect();
+            Bvh::build(&bounds)
+        })
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Group::new()
+    }
+}
+
+impl Shape for Group {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        self.intersect(ray).into_iter().map(|hit| hit.t).collect()
+    }
+
+    // Which child a point belongs to isn't known from the point alone, so
+    // this goes through a hit. Prefer Intersection::normal_at when the hit
+    // is already to hand.
+    fn local_normal_at(&self, point: Point3) -> Vector3 {
+        normal_through_hits(self, point)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.child_bounds.transform(self.transform)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let local_ray = ray.transform(self.inverse);
+        if !self.child_bounds.intersects(&local_ray) {
+            return vec![];
+        }
+        let mut hits: Vec<Intersection> = self.bvh().candidates(&local_ray).into_iter()
+            .flat_map(|index| self.children[index].intersect(&local_ray))
+            .map(|hit| hit.transformed(self.inverse))
+            .collect();
+        hits.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+    use crate::shapes::sphere::Sphere;
+    use crate::intersection::hit;
+
+    fn translation(x: f64, y: f64, z: f64) -> Matrix4 {
+        Matrix4::new([
+            1.0, 0.0, 0.0, x,
+            0.0, 1.0, 0.0, y,
+            0.0, 0.0, 1.0, z,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    fn scaling(x: f64, y: f64, z: f64) -> Matrix4 {
+        Matrix4::new([
+            x, 0.0, 0.0, 0.0,
+            0.0, y, 0.0, 0.0,
+            0.0, 0.0, z, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    fn rotation_y(radians: f64) -> Matrix4 {
+        Matrix4::new([
+            radians.cos(), 0.0, radians.sin(), 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            -radians.sin(), 0.0, radians.cos(), 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    fn sphere_at(transform: Matrix4) -> Box<dyn Shape> {
+        let mut g = Group::with_transform(transform).unwrap();
+        g.add_child(Box::new(Sphere::new()));
+        Box::new(g)
+    }
+
+    #[test]
+    fn intersecting_a_ray_with_an_empty_group() {
+        let g = Group::new();
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(g.intersect(&r).is_empty());
+        assert!(g.bounds().is_empty());
+    }
+
+    #[test]
+    fn intersecting_a_ray_with_a_nonempty_group() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        g.add_child(sphere_at(translation(0.0, 0.0, -3.0)));
+        g.add_child(sphere_at(translation(5.0, 0.0, 0.0)));
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let ts: Vec<f64> = g.intersect(&r).iter().map(|hit| hit.t).collect();
+
+        assert_eq!(g.children().len(), 3);
+        assert_eq!(ts, vec![1.0, 3.0, 4.0, 6.0]);
+        assert_eq!(g.local_intersect(&r), ts);
+    }
+
+    #[test]
+    fn intersecting_a_transformed_group() {
+        let mut g = Group::with_transform(scaling(2.0, 2.0, 2.0)).unwrap();
+        g.add_child(sphere_at(translation(5.0, 0.0, 0.0)));
+        let r = Ray::new(Point3::new(10.0, 0.0, -10.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(g.intersect(&r).len(), 2);
+    }
+
+    #[test]
+    fn a_singular_group_transform_is_rejected() {
+        assert!(Group::with_transform(scaling(0.0, 1.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn group_bounds_enclose_the_transformed_children() {
+        let mut g = Group::with_transform(translation(0.0, 10.0, 0.0)).unwrap();
+        g.add_child(sphere_at(translation(2.0, 0.0, 0.0)));
+        g.add_child(sphere_at(translation(-2.0, 0.0, 0.0) * scaling(0.5, 0.5, 0.5)));
+        let b = g.bounds();
+
+        assert_eq!(b.min, Point3::new(-2.5, 9.0, -1.0));
+        assert_eq!(b.max, Point3::new(3.0, 11.0, 1.0));
+    }
+
+    // Sphere placements spread through a 40-unit cube.
+    fn scattered_placements(count: usize, mut seed: u64) -> Vec<Matrix4> {
+        let mut random = move || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+        (0..count).map(|_| {
+            let (x, y, z) = (random() * 40.0 - 20.0, random() * 40.0 - 20.0, random() * 40.0 - 20.0);
+            let radius = 0.2 + random();
+            translation(x, y, z) * scaling(radius, radius, radius)
+        }).collect()
+    }
+
+    #[test]
+    fn the_group_bvh_finds_the_same_hits_as_brute_force() {
+        let inner_transform = translation(0.0, 0.0, 30.0) * rotation_y(PI / 3.0);
+        let mut scene = Group::new();
+        let mut inner = Group::with_transform(inner_transform).unwrap();
+        let mut placements = scattered_placements(400, 1);
+        for placement in placements.iter() {
+            scene.add_child(sphere_at(*placement));
+        }
+        for placement in scattered_placements(200, 2) {
+            inner.add_child(sphere_at(placement));
+            placements.push(inner_transform * placement);
+        }
+        scene.add_child(Box::new(inner));
+        let inverses: Vec<Matrix4> = placements.iter().map(|placement| placement.inverse().unwrap()).collect();
+
+        let mut hit_rays = 0;
+        for i in 0..20 {
+            for j in 0..20 {
+                let origin = Point3::new(-25.0 + 2.5 * i as f64, -25.0 + 2.5 * j as f64, -60.0);
+                let r = Ray::new(origin, Vector3::new(0.1 * j as f64 - 1.0, 0.05 * i as f64 - 0.5, 10.0));
+                let ts: Vec<f64> = scene.intersect(&r).iter().map(|hit| hit.t).collect();
+                let mut expected: Vec<f64> = inverses.iter()
+                    .flat_map(|inverse| Sphere::new().local_intersect(&r.transform(*inverse)))
+                    .collect();
+                expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+                assert_eq!(ts.len(), expected.len());
+                for (t, e) in ts.iter().zip(expected.iter()) {
+                    assert!((t - e).abs() < 1e-9);
+                }
+                if !ts.is_empty() {
+                    hit_rays += 1;
+                }
+            }
+        }
+        assert!(hit_rays > 40 && hit_rays < 400);
+    }
+
+    #[test]
+    fn a_ray_only_tests_a_fraction_of_the_children() {
+        let mut scene = Group::new();
+        for placement in scattered_placements(400, 1) {
+            scene.add_child(sphere_at(placement));
+        }
+        let r = Ray::new(Point3::new(0.0, 0.0, -60.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(scene.bvh().candidates(&r).len() < scene.children().len() / 10);
+    }
+
+    fn nested(inner: Matrix4) -> Group {
+        let mut g2 = Group::with_transform(inner).unwrap();
+        g2.add_child(sphere_at(translation(5.0, 0.0, 0.0)));
+        let mut g1 = Group::with_transform(rotation_y(PI / 2.0)).unwrap();
+        g1.add_child(Box::new(g2));
+        g1
+    }
+
+    #[test]
+    fn converting_a_point_from_world_to_object_space() {
+        let g1 = nested(scaling(2.0, 2.0, 2.0));
+        let r = Ray::new(Point3::new(0.0, 0.0, 20.0), Vector3::new(0.0, 0.0, -1.0));
+        let hits = g1.intersect(&r);
+        let h = hit(&hits).unwrap();
+
+        assert_eq!(h.world_to_object(Point3::new(-2.0, 0.0, -10.0)), Point3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn converting_a_normal_from_object_to_world_space() {
+        let g1 = nested(scaling(1.0, 2.0, 3.0));
+        let r = Ray::new(Point3::new(0.0, 0.0, 20.0), Vector3::new(0.0, 0.0, -1.0));
+        let hits = g1.intersect(&r);
+        let k = 3.0_f64.sqrt() / 3.0;
+
+        assert_eq!(hits[0].normal_to_world(Vector3::new(k, k, k)), Vector3::new(0.28571, 0.42857, -0.85714));
+    }
+
+    #[test]
+    fn finding_the_normal_on_a_child_object() {
+        let g1 = nested(scaling(1.0, 2.0, 3.0));
+        let point = Point3::new(1.7321, 1.1547, -5.5774);
+        let outward = Vector3::new(0.2857, 0.4286, -0.8571);
+        let r = Ray::new(point + outward * 10.0, outward * -1.0);
+        let hits = g1.intersect(&r);
+        let h = hit(&hits).unwrap();
+        let n = h.normal_at(r.position(h.t));
+
+        assert!((n.0 - 0.2857).abs() < 1e-3);
+        assert!((n.1 - 0.4286).abs() < 1e-3);
+        assert!((n.2 + 0.8571).abs() < 1e-3);
+    }
+
+    #[test]
+    fn a_group_finds_its_normal_through_a_hit() {
+        let g1 = nested(scaling(1.0, 2.0, 3.0));
+        let n = g1.local_normal_at(Point3::new(1.7321, 1.1547, -5.5774));
+
+        assert!((n.0 - 0.2857).abs() < 1e-3);
+        assert!((n.1 - 0.4286).abs() < 1e-3);
+        assert!((n.2 + 0.8571).abs() < 1e-3);
+    }
+
+    #[test]
+    fn an_empty_group_still_has_a_normal() {
+        let n = Group::new().local_normal_at(Point3::new(0.0, 0.0, 0.0));
+
+        assert_eq!(n, Vector3::new(0.0, 1.0, 0.0));
+    }
+}
@@ -0,0 +1,318 @@
+use std::fmt;
+use super::Shape;
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::canvas::Canvas;
+use crate::roots::solve_quadratic;
+
+const EPSILON: f64 = 0.00001;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightFieldError {
+    pub message: String
+}
+
+impl HeightFieldError {
+    fn new(message: String) -> HeightFieldError {
+        HeightFieldError {
+            message
+        }
+    }
+}
+
+impl fmt::Display for HeightFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid height field: {}", self.message)
+    }
+}
+
+impl std::error::Error for HeightFieldError {}
+
+// Terrain over the unit square in x and z, with one elevation sample per
+// canvas pixel: columns run along x, rows along z, and the pixel's luminance
+// is its height. Between samples the surface is bilinearly interpolated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightField {
+    columns: usize,
+    rows: usize,
+    heights: Vec<f64>,
+    min_height: f64,
+    max_height: f64
+}
+
+impl HeightField {
+    // Every cell needs a sample at each corner, so the canvas must be at
+    // least 2x2.
+    pub fn from_canvas(canvas: &Canvas) -> Result<HeightField, HeightFieldError> {
+        let columns = canvas.width();
+        let rows = canvas.height();
+        if columns < 2 || rows < 2 {
+            return Err(HeightFieldError::new(format!(
+                "needs at least 2x2 samples, got {}x{}", columns, rows
+            )));
+        }
+        let mut heights = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                heights.push(canvas.pixel_at(column, row).luminance());
+            }
+        }
+        let min_height = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max_height = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        Ok(HeightField {
+            columns,
+            rows,
+            heights,
+            min_height,
+            max_height
+        })
+    }
+
+    pub fn height_at(&self, column: usize, row: usize) -> f64 {
+        self.heights[row * self.columns + column]
+    }
+
+    fn cell_scale(&self) -> (f64, f64) {
+        ((self.columns - 1) as f64, (self.rows - 1) as f64)
+    }
+
+    // Bilinear patch coefficients for a cell: h = h00 + a*fx + b*fz + c*fx*fz,
+    // with fx and fz the fractional position inside the cell.
+    fn cell_coefficients(&self, column: usize, row: usize) -> (f64, f64, f64, f64) {
+        let h00 = self.height_at(column, row);
+        let h10 = self.height_at(column + 1, row);
+        let h01 = self.height_at(column, row + 1);
+        let h11 = self.height_at(column + 1, row + 1);
+        (h00, h10 - h00, h01 - h00, h00 - h10 - h01 + h11)
+    }
+
+    fn intersect_cell(&self, ray: &Ray, column: usize, row: usize, enter: f64, exit: f64) -> Vec<f64> {
+        let (scale_x, scale_z) = self.cell_scale();
+        let (h00, a, b, c) = self.cell_coefficients(column, row);
+        let px = ray.origin.0 * scale_x - column as f64;
+        let qx = ray.direction.0 * scale_x;
+        let pz = ray.origin.2 * scale_z - row as f64;
+        let qz = ray.direction.2 * scale_z;
+
+        let k0 = h00 + a * px + b * pz + c * px * pz;
+        let k1 = a * qx + b * qz + c * (px * qz + qx * pz);
+        let k2 = c * qx * qz;
+        solve_quadratic(-k2, ray.direction.1 - k1, ray.origin.1 - k0)
+            .into_iter()
+            .filter(|t| *t >= enter - EPSILON && *t <= exit + EPSILON)
+            .collect()
+    }
+}
+
+impl Shape for HeightField {
+    // Walks the cells under the ray with a 2D DDA, solving the bilinear patch
+    // of each cell the ray passes over.
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        let (start, end) = match self.bounds().intersection_range(ray) {
+            Some(range) => range,
+            None => return vec![]
+        };
+        let (scale_x, scale_z) = self.cell_scale();
+        let last_column = self.columns as i64 - 2;
+        let last_row = self.rows as i64 - 2;
+
+        let entry = ray.position(start);
+        let mut column = ((entry.0 * scale_x).floor() as i64).clamp(0, last_column);
+        let mut row = ((entry.2 * scale_z).floor() as i64).clamp(0, last_row);
+
+        let step_column: i64 = if ray.direction.0 > 0.0 { 1 } else { -1 };
+        let step_row: i64 = if ray.direction.2 > 0.0 { 1 } else { -1 };
+        let boundary_time = |cell: i64, step: i64, scale: f64, origin: f64, direction: f64| {
+            if direction.abs() < EPSILON {
+                return f64::INFINITY;
+            }
+            let boundary = if step > 0 { cell + 1 } else { cell };
+            (boundary as f64 / scale - origin) / direction
+        };
+        let mut next_column = boundary_time(column, step_column, scale_x, ray.origin.0, ray.direction.0);
+        let mut next_row = boundary_time(row, step_row, scale_z, ray.origin.2, ray.direction.2);
+        let delta_column = 1.0 / (scale_x * ray.direction.0.abs());
+        let delta_row = 1.0 / (scale_z * ray.direction.2.abs());
+
+        let mut hits: Vec<f64> = vec![];
+        let mut enter = start;
+        while enter <= end {
+            let exit = next_column.min(next_row).min(end);
+            for t in self.intersect_cell(ray, column as usize, row as usize, enter, exit) {
+                // A hit on a shared cell edge is found from both sides.
+                let duplicate = match hits.last() {
+                    Some(last) => (t - last).abs() <= EPSILON,
+                    None => false
+                };
+                if !duplicate {
+                    hits.push(t);
+                }
+            }
+            if exit >= end {
+                break;
+            }
+            if next_column < next_row {
+                column += step_column;
+                next_column += delta_column;
+            } else {
+                row += step_row;
+                next_row += delta_row;
+            }
+            if column < 0 || column > last_column || row < 0 || row > last_row {
+                break;
+            }
+            enter = exit;
+        }
+        hits
+    }
+
+    fn local_normal_at(&self, point: Point3) -> Vector3 {
+        let (scale_x, scale_z) = self.cell_scale();
+        let u = point.0 * scale_x;
+        let v = point.2 * scale_z;
+        let column = (u.floor() as i64).clamp(0, self.columns as i64 - 2) as usize;
+        let row = (v.floor() as i64).clamp(0, self.rows as i64 - 2) as usize;
+        let (_, a, b, c) = self.cell_coefficients(column, row);
+        let fx = u - column as f64;
+        let fz = v - row as f64;
+        Vector3::new(
+            -(a + c * fz) * scale_x,
+            1.0,
+            -(b + c * fx) * scale_z
+        ).normalize()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point3::new(0.0, self.min_height, 0.0),
+            Point3::new(1.0, self.max_height, 1.0)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx_equal::approx_equal::equal;
+    use crate::color::Color;
+
+    fn flat(width: usize, height: usize, level: f64) -> HeightField {
+        let mut canvas = Canvas::new(width, height);
+        for x in 0..width {
+            for y in 0..height {
+                canvas.write_pixel(x, y, Color::new(level, level, level));
+            }
+        }
+        HeightField::from_canvas(&canvas).unwrap()
+    }
+
+    // Heights rise linearly from 0 at x = 0 to 1 at x = 1.
+    fn ramp() -> HeightField {
+        let mut canvas = Canvas::new(5, 3);
+        for x in 0..5 {
+            for y in 0..3 {
+                let level = x as f64 / 4.0;
+                canvas.write_pixel(x, y, Color::new(level, level, level));
+            }
+        }
+        HeightField::from_canvas(&canvas).unwrap()
+    }
+
+    #[test]
+    fn heights_come_from_pixel_luminance() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.write_pixel(1, 0, Color::new(1.0, 0.0, 0.0));
+        canvas.write_pixel(0, 1, Color::new(0.0, 1.0, 0.0));
+        canvas.write_pixel(1, 1, Color::new(1.0, 1.0, 1.0));
+        let field = HeightField::from_canvas(&canvas).unwrap();
+
+        assert_eq!(field.height_at(0, 0), 0.0);
+        assert!(equal(field.height_at(1, 0), 0.2126));
+        assert!(equal(field.height_at(0, 1), 0.7152));
+        assert!(equal(field.height_at(1, 1), 1.0));
+    }
+
+    #[test]
+    fn a_ray_hits_a_flat_height_field() {
+        let field = flat(4, 4, 0.5);
+        let r = Ray::new(Point3::new(0.3, 5.0, 0.7), Vector3::new(0.0, -1.0, 0.0));
+        let hits = field.local_intersect(&r);
+
+        assert_eq!(hits.len(), 1);
+        assert!(equal(hits[0], 4.5));
+    }
+
+    #[test]
+    fn a_ray_misses_outside_the_terrain() {
+        let field = flat(4, 4, 0.5);
+        let beside = Ray::new(Point3::new(1.5, 5.0, 0.5), Vector3::new(0.0, -1.0, 0.0));
+        let above = Ray::new(Point3::new(-1.0, 0.8, 0.5), Vector3::new(1.0, 0.0, 0.0));
+
+        assert!(field.local_intersect(&beside).is_empty());
+        assert!(field.local_intersect(&above).is_empty());
+    }
+
+    #[test]
+    fn a_horizontal_ray_hits_a_slope_across_cells() {
+        let field = ramp();
+        let r = Ray::new(Point3::new(-1.0, 0.6, 0.4), Vector3::new(1.0, 0.0, 0.0));
+        let hits = field.local_intersect(&r);
+
+        assert_eq!(hits.len(), 1);
+        assert!(equal(hits[0], 1.6));
+    }
+
+    #[test]
+    fn a_hit_on_a_cell_edge_is_reported_once() {
+        let field = ramp();
+        let r = Ray::new(Point3::new(-1.0, 0.5, 0.4), Vector3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(field.local_intersect(&r).len(), 1);
+    }
+
+    #[test]
+    fn a_diagonal_ray_over_a_bump_enters_and_leaves() {
+        let mut canvas = Canvas::new(3, 3);
+        canvas.write_pixel(1, 1, Color::new(1.0, 1.0, 1.0));
+        let field = HeightField::from_canvas(&canvas).unwrap();
+        let r = Ray::new(Point3::new(-1.0, 0.5, -1.0), Vector3::new(1.0, 0.0, 1.0));
+        let hits = field.local_intersect(&r);
+
+        // Along the diagonal the bilinear patch rises as 4s^2 towards the peak.
+        let s = 0.125_f64.sqrt();
+
+        assert_eq!(hits.len(), 2);
+        assert!(equal(hits[0], 1.0 + s));
+        assert!(equal(hits[1], 2.0 - s));
+    }
+
+    #[test]
+    fn the_normal_follows_the_slope() {
+        let field = ramp();
+
+        assert_eq!(
+            field.local_normal_at(Point3::new(0.3, 0.3, 0.5)),
+            Vector3::new(-1.0, 1.0, 0.0).normalize()
+        );
+        assert_eq!(flat(3, 3, 0.2).local_normal_at(Point3::new(0.5, 0.2, 0.5)), Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn a_height_field_is_bounded_by_its_samples() {
+        let b = ramp().bounds();
+
+        assert_eq!(b.min, Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(b.max, Point3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn a_canvas_too_small_for_one_cell_is_an_error() {
+        let error = HeightField::from_canvas(&Canvas::new(1, 5)).unwrap_err();
+
+        assert_eq!(error.message, "needs at least 2x2 samples, got 1x5");
+        assert!(HeightField::from_canvas(&Canvas::new(3, 0)).is_err());
+        assert!(HeightField::from_canvas(&Canvas::new(2, 2)).is_ok());
+    }
+}
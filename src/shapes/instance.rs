@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use super::{Shape, normal_through_hits};
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::intersection::Intersection;
+use crate::matrix::matrix4::Matrix4;
+
+// Places a shared shape under its own transform. Many instances can point
+// at the same shape without copying it.
+#[derive(Clone)]
+pub struct Instance {
+    pub shape: Arc<dyn Shape>,
+    transform: Matrix4,
+    inverse: Matrix4
+}
+
+impl Instance {
+    // None when the transform can't be inverted, e.g. a zero scale.
+    pub fn new(shape: Arc<dyn Shape>, transform: Matrix4) -> Option<Instance> {
+        let inverse = transform.inverse()?;
+        Some(Instance {
+            shape,
+            transform,
+            inverse
+        })
+    }
+
+    pub fn transform(&self) -> Matrix4 {
+        self.transform
+    }
+}
+
+impl Shape for Instance {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        self.shape.local_intersect(&ray.transform(self.inverse))
+    }
+
+    // Taken through a hit, since the shared shape may be a group or mesh
+    // whose normal depends on which child was hit.
+    fn local_normal_at(&self, point: Point3) -> Vector3 {
+        normal_through_hits(self, point)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.shape.bounds().transform(self.transform)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.shape.intersect(&ray.transform(self.inverse)).into_iter()
+            .map(|hit| hit.transformed(self.inverse))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::torus::Torus;
+    use crate::shapes::disk::Disk;
+    use crate::shapes::triangle::SmoothTriangle;
+    use crate::shapes::group::Group;
+    use crate::shapes::sphere::Sphere;
+
+    fn translation(x: f64, y: f64, z: f64) -> Matrix4 {
+        Matrix4::new([
+            1.0, 0.0, 0.0, x,
+            0.0, 1.0, 0.0, y,
+            0.0, 0.0, 1.0, z,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    fn scaling(x: f64, y: f64, z: f64) -> Matrix4 {
+        Matrix4::new([
+            x, 0.0, 0.0, 0.0,
+            0.0, y, 0.0, 0.0,
+            0.0, 0.0, z, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    #[test]
+    fn instances_share_one_shape() {
+        let shared: Arc<dyn Shape> = Arc::new(Torus::new(1.0, 0.25));
+        let first = Instance::new(Arc::clone(&shared), translation(5.0, 0.0, 0.0)).unwrap();
+        let second = Instance::new(Arc::clone(&shared), translation(-5.0, 0.0, 0.0)).unwrap();
+
+        assert_eq!(Arc::strong_count(&shared), 3);
+        assert!(Arc::ptr_eq(&first.shape, &second.shape));
+    }
+
+    #[test]
+    fn intersecting_a_translated_instance() {
+        let shared: Arc<dyn Shape> = Arc::new(Disk::new(1.0));
+        let instance = Instance::new(shared, translation(5.0, 2.0, 0.0)).unwrap();
+        let hit = Ray::new(Point3::new(5.5, 10.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let miss = Ray::new(Point3::new(0.0, 10.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        assert_eq!(instance.local_intersect(&hit), vec![8.0]);
+        assert!(instance.local_intersect(&miss).is_empty());
+    }
+
+    #[test]
+    fn intersecting_a_scaled_instance() {
+        let shared: Arc<dyn Shape> = Arc::new(Disk::new(1.0));
+        let instance = Instance::new(shared, scaling(3.0, 3.0, 3.0)).unwrap();
+        let r = Ray::new(Point3::new(2.5, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        assert_eq!(instance.local_intersect(&r), vec![5.0]);
+    }
+
+    #[test]
+    fn instance_normals_use_the_inverse_transpose() {
+        let shared: Arc<dyn Shape> = Arc::new(Torus::new(1.0, 0.25));
+        let instance = Instance::new(shared, translation(0.0, 1.0, 0.0) * scaling(1.0, 0.5, 1.0)).unwrap();
+        let n = instance.local_normal_at(Point3::new(1.0, 1.125, 0.0));
+
+        assert_eq!(n, Vector3::new(0.0, 1.0, 0.0));
+        let side = instance.local_normal_at(Point3::new(1.25, 1.0, 0.0));
+        assert_eq!(side, Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn instance_bounds_follow_the_transform() {
+        let shared: Arc<dyn Shape> = Arc::new(Torus::new(1.0, 0.25));
+        let instance = Instance::new(shared, translation(5.0, 0.0, 0.0) * scaling(2.0, 2.0, 2.0)).unwrap();
+        let b = instance.bounds();
+
+        assert_eq!(b.min, Point3::new(2.5, -0.5, -2.5));
+        assert_eq!(b.max, Point3::new(7.5, 0.5, 2.5));
+    }
+
+    #[test]
+    fn hits_through_an_instance_keep_the_child_uv_and_normal() {
+        let shared: Arc<dyn Shape> = Arc::new(SmoothTriangle::new(
+            [Point3::new(0.0, 1.0, 0.0), Point3::new(-1.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0)],
+            [Vector3::new(0.0, 1.0, 0.0), Vector3::new(-1.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)]
+        ));
+        let instance = Instance::new(shared, translation(5.0, 0.0, 0.0) * scaling(2.0, 2.0, 2.0)).unwrap();
+        let r = Ray::new(Point3::new(4.6, 0.6, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = instance.intersect(&r);
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].t, 2.0);
+        assert!((hits[0].u - 0.45).abs() < 1e-9);
+        assert!((hits[0].v - 0.25).abs() < 1e-9);
+        assert_eq!(hits[0].normal_at(r.position(hits[0].t)), Vector3::new(-0.5547, 0.83205, 0.0));
+    }
+
+    #[test]
+    fn shading_an_instanced_group() {
+        let mut group = Group::with_transform(translation(0.0, 0.0, 5.0)).unwrap();
+        group.add_child(Box::new(Sphere::new()));
+        let shared: Arc<dyn Shape> = Arc::new(group);
+        let instance = Instance::new(shared, scaling(1.0, 2.0, 1.0)).unwrap();
+        let r = Ray::new(Point3::new(0.0, 1.2, 10.0), Vector3::new(0.0, 0.0, -1.0));
+        let hits = instance.intersect(&r);
+        let expected = Vector3::new(0.0, 0.35112, 0.93633);
+
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0].t - 4.2).abs() < 1e-9);
+        let point = r.position(hits[0].t);
+        assert_eq!(hits[0].normal_at(point), expected);
+        assert_eq!(instance.local_normal_at(point), expected);
+    }
+
+    #[test]
+    fn a_singular_transform_is_rejected() {
+        let shared: Arc<dyn Shape> = Arc::new(Disk::new(1.0));
+
+        assert!(Instance::new(Arc::clone(&shared), scaling(1.0, 0.0, 1.0)).is_none());
+        assert!(Instance::new(shared, scaling(1.0, 2.0, 1.0)).is_some());
+    }
+
+    #[test]
+    fn instances_can_be_shared_across_threads() {
+        let shared: Arc<dyn Shape> = Arc::new(Torus::new(1.0, 0.25));
+        let instance = Arc::new(Instance::new(shared, translation(0.0, 0.0, 5.0)).unwrap());
+        let r = Ray::new(Point3::new(1.0, 5.0, 5.0), Vector3::new(0.0, -1.0, 0.0));
+
+        let handles: Vec<_> = (0..4).map(|_| {
+            let instance = Arc::clone(&instance);
+            std::thread::spawn(move || instance.local_intersect(&r).len())
+        }).collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 2);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use super::Shape;
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::roots::solve_quadratic;
+
+const BISECTION_ITERATIONS: usize = 40;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Metaball {
+    pub center: Point3,
+    pub radius: f64
+}
+
+impl Metaball {
+    pub fn new(center: Point3, radius: f64) -> Metaball {
+        Metaball {
+            center,
+            radius
+        }
+    }
+
+    // Falls smoothly from 1 at the centre to 0 at the radius and stays 0
+    // beyond it, so each ball only influences its own bounding sphere.
+    fn field(&self, point: Point3) -> f64 {
+        let offset = point - self.center;
+        let s = (offset * offset) / (self.radius * self.radius);
+        if s >= 1.0 {
+            return 0.0;
+        }
+        (1.0 - s) * (1.0 - s) * (1.0 - s)
+    }
+
+    fn gradient(&self, point: Point3) -> Vector3 {
+        let offset = point - self.center;
+        let radius_squared = self.radius * self.radius;
+        let s = (offset * offset) / radius_squared;
+        if s >= 1.0 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        offset * (-6.0 * (1.0 - s) * (1.0 - s) / radius_squared)
+    }
+
+    fn sphere_span(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let to_origin = ray.origin - self.center;
+        let roots = solve_quadratic(
+            ray.direction * ray.direction,
+            2.0 * (ray.direction * to_origin),
+            to_origin * to_origin - self.radius * self.radius
+        );
+        if roots.len() < 2 {
+            return None;
+        }
+        Some((roots[0], roots[1]))
+    }
+}
+
+// Blobby surface where the summed field of all the balls equals threshold.
+// Lower thresholds give fatter, more merged shapes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metaballs {
+    pub balls: Vec<Metaball>,
+    pub threshold: f64,
+    pub samples: usize
+}
+
+impl Metaballs {
+    pub fn new(balls: Vec<Metaball>, threshold: f64) -> Metaballs {
+        Metaballs {
+            balls,
+            threshold,
+            samples: 64
+        }
+    }
+
+    pub fn field(&self, point: Point3) -> f64 {
+        self.balls.iter().map(|ball| ball.field(point)).sum()
+    }
+
+    fn value_along(&self, ray: &Ray, t: f64) -> f64 {
+        self.field(ray.position(t)) - self.threshold
+    }
+
+    fn refine(&self, ray: &Ray, mut low: f64, mut high: f64) -> f64 {
+        let low_sign = self.value_along(ray, low) > 0.0;
+        for _ in 0..BISECTION_ITERATIONS {
+            let middle = (low + high) / 2.0;
+            if (self.value_along(ray, middle) > 0.0) == low_sign {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        (low + high) / 2.0
+    }
+}
+
+impl Shape for Metaballs {
+    // The field is zero outside every ball, so only the spans where the ray
+    // crosses a bounding sphere are sampled for sign changes.
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        let mut spans: Vec<(f64, f64)> = self.balls.iter()
+            .filter_map(|ball| ball.sphere_span(ray))
+            .collect();
+        spans.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let mut merged: Vec<(f64, f64)> = vec![];
+        for span in spans {
+            match merged.last_mut() {
+                Some(last) if span.0 <= last.1 => last.1 = last.1.max(span.1),
+                _ => merged.push(span)
+            }
+        }
+
+        let mut hits = vec![];
+        for (start, end) in merged {
+            let step = (end - start) / self.samples as f64;
+            let mut previous = self.value_along(ray, start);
+            for i in 1..=self.samples {
+                let t = start + step * i as f64;
+                let current = self.value_along(ray, t);
+                if (previous > 0.0) != (current > 0.0) {
+                    hits.push(self.refine(ray, t - step, t));
+                }
+                previous = current;
+            }
+        }
+        hits
+    }
+
+    fn local_normal_at(&self, point: Point3) -> Vector3 {
+        let gradient = self.balls.iter()
+            .fold(Vector3::new(0.0, 0.0, 0.0), |acc, ball| acc + ball.gradient(point));
+        (-gradient).normalize()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.balls.iter().fold(BoundingBox::empty(), |acc, ball| {
+            let extent = Vector3::new(ball.radius, ball.radius, ball.radius);
+            acc.union(&BoundingBox::new(ball.center - extent, ball.center + extent))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx_equal::approx_equal::equal;
+
+    fn single_ball() -> Metaballs {
+        Metaballs::new(vec![Metaball::new(Point3::new(0.0, 0.0, 0.0), 1.0)], 0.125)
+    }
+
+    #[test]
+    fn a_single_ball_is_a_sphere_inside_its_radius() {
+        let m = single_ball();
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = m.local_intersect(&r);
+        let surface = 0.5_f64.sqrt();
+
+        assert_eq!(hits.len(), 2);
+        assert!(equal(hits[0], 5.0 - surface));
+        assert!(equal(hits[1], 5.0 + surface));
+    }
+
+    #[test]
+    fn a_ray_misses_outside_the_threshold_surface() {
+        let m = single_ball();
+        let r = Ray::new(Point3::new(0.0, 0.8, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(m.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn nearby_balls_merge_into_one_blob() {
+        let m = Metaballs::new(vec![
+            Metaball::new(Point3::new(-0.6, 0.0, 0.0), 1.0),
+            Metaball::new(Point3::new(0.6, 0.0, 0.0), 1.0)
+        ], 0.125);
+        let between = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let across = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(m.local_intersect(&between).len(), 2);
+        assert_eq!(m.local_intersect(&across).len(), 2);
+    }
+
+    #[test]
+    fn distant_balls_stay_separate() {
+        let m = Metaballs::new(vec![
+            Metaball::new(Point3::new(-3.0, 0.0, 0.0), 1.0),
+            Metaball::new(Point3::new(3.0, 0.0, 0.0), 1.0)
+        ], 0.125);
+        let across = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(m.local_intersect(&across).len(), 4);
+    }
+
+    #[test]
+    fn the_normal_points_away_from_the_blob() {
+        let m = single_ball();
+        let surface = 0.5_f64.sqrt();
+
+        assert_eq!(m.local_normal_at(Point3::new(surface, 0.0, 0.0)), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(m.local_normal_at(Point3::new(0.0, 0.0, -surface)), Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn metaballs_are_bounded_by_their_spheres() {
+        let m = Metaballs::new(vec![
+            Metaball::new(Point3::new(-1.0, 0.0, 0.0), 1.0),
+            Metaball::new(Point3::new(2.0, 1.0, 0.0), 0.5)
+        ], 0.5);
+        let b = m.bounds();
+
+        assert_eq!(b.min, Point3::new(-2.0, -1.0, -1.0));
+        assert_eq!(b.max, Point3::new(2.5, 1.5, 1.0));
+    }
+}
@@ -0,0 +1,140 @@
+use super::Shape;
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::intersection::Intersection;
+
+const EPSILON: f64 = 0.00001;
+
+// An axis-aligned rectangle in the xz plane facing +y, centred on the origin.
+// Hits carry uv_at's coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rectangle {
+    pub width: f64,
+    pub depth: f64
+}
+
+impl Rectangle {
+    pub fn new(width: f64, depth: f64) -> Rectangle {
+        Rectangle {
+            width,
+            depth
+        }
+    }
+
+    // u runs along x and v along z, both from 0 at the minimum edge to 1.
+    pub fn uv_at(&self, point: Point3) -> (f64, f64) {
+        (
+            point.0 / self.width + 0.5,
+            point.2 / self.depth + 0.5
+        )
+    }
+
+    pub fn area(&self) -> f64 {
+        self.width * self.depth
+    }
+
+    pub fn sample_point(&self, u: f64, v: f64) -> Point3 {
+        Point3::new((u - 0.5) * self.width, 0.0, (v - 0.5) * self.depth)
+    }
+
+    // t and the point of the hit, if any.
+    fn hit(&self, ray: &Ray) -> Option<(f64, Point3)> {
+        if ray.direction.1.abs() < EPSILON {
+            return None;
+        }
+        let t = -ray.origin.1 / ray.direction.1;
+        let point = ray.position(t);
+        if point.0.abs() > self.width / 2.0 || point.2.abs() > self.depth / 2.0 {
+            return None;
+        }
+        Some((t, point))
+    }
+}
+
+impl Shape for Rectangle {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        self.hit(ray).map(|(t, _)| t).into_iter().collect()
+    }
+
+    fn local_normal_at(&self, _point: Point3) -> Vector3 {
+        Vector3::new(0.0, 1.0, 0.0)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point3::new(-self.width / 2.0, 0.0, -self.depth / 2.0),
+            Point3::new(self.width / 2.0, 0.0, self.depth / 2.0)
+        )
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.hit(ray).map(|(t, point)| {
+            let (u, v) = self.uv_at(point);
+            Intersection::with_uv(t, self, u, v)
+        }).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_ray_hits_a_rectangle() {
+        let rect = Rectangle::new(4.0, 2.0);
+        let r = Ray::new(Point3::new(1.9, 3.0, -0.9), Vector3::new(0.0, -1.0, 0.0));
+
+        assert_eq!(rect.local_intersect(&r), vec![3.0]);
+    }
+
+    #[test]
+    fn a_ray_misses_past_the_edges() {
+        let rect = Rectangle::new(4.0, 2.0);
+        let past_x = Ray::new(Point3::new(2.1, 3.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let past_z = Ray::new(Point3::new(0.0, 3.0, 1.1), Vector3::new(0.0, -1.0, 0.0));
+        let parallel = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(rect.local_intersect(&past_x).is_empty());
+        assert!(rect.local_intersect(&past_z).is_empty());
+        assert!(rect.local_intersect(&parallel).is_empty());
+    }
+
+    #[test]
+    fn uv_coordinates_on_a_rectangle() {
+        let rect = Rectangle::new(4.0, 2.0);
+
+        assert_eq!(rect.uv_at(Point3::new(-2.0, 0.0, -1.0)), (0.0, 0.0));
+        assert_eq!(rect.uv_at(Point3::new(0.0, 0.0, 0.0)), (0.5, 0.5));
+        assert_eq!(rect.uv_at(Point3::new(1.0, 0.0, 1.0)), (0.75, 1.0));
+    }
+
+    #[test]
+    fn hits_carry_uv_coordinates() {
+        let rect = Rectangle::new(4.0, 2.0);
+        let r = Ray::new(Point3::new(1.0, 3.0, 1.0), Vector3::new(0.0, -1.0, 0.0));
+        let hits = rect.intersect(&r);
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].t, hits[0].u, hits[0].v), (3.0, 0.75, 1.0));
+    }
+
+    #[test]
+    fn sampling_and_uv_are_inverses() {
+        let rect = Rectangle::new(4.0, 2.0);
+        let p = rect.sample_point(0.25, 0.8);
+
+        assert_eq!(p, Point3::new(-1.0, 0.0, 0.6));
+        assert_eq!(rect.uv_at(p), (0.25, 0.8));
+        assert_eq!(rect.area(), 8.0);
+    }
+
+    #[test]
+    fn a_rectangle_has_flat_bounds() {
+        let b = Rectangle::new(4.0, 2.0).bounds();
+
+        assert_eq!(b.min, Point3::new(-2.0, 0.0, -1.0));
+        assert_eq!(b.max, Point3::new(2.0, 0.0, 1.0));
+    }
+}
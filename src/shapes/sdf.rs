@@ -0,0 +1,193 @@
+use super::Shape;
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+
+// A surface defined implicitly by a signed distance function: negative
+// inside, positive outside, zero on the surface. Rays are intersected by
+// sphere tracing, stepping forward by the distance to the nearest surface.
+pub struct SdfShape {
+    pub distance: Box<dyn Fn(Point3) -> f64 + Send + Sync>,
+    pub max_steps: usize,
+    pub epsilon: f64,
+    pub max_distance: f64,
+    pub bounds: BoundingBox
+}
+
+impl SdfShape {
+    pub fn new<F: Fn(Point3) -> f64 + Send + Sync + 'static>(distance: F) -> SdfShape {
+        SdfShape {
+            distance: Box::new(distance),
+            max_steps: 256,
+            epsilon: 0.0001,
+            max_distance: 100.0,
+            bounds: BoundingBox::infinite()
+        }
+    }
+}
+
+// Polynomial smooth minimum, for blending two distance fields into one
+// without a crease. k is roughly the width of the blend.
+pub fn smooth_min(a: f64, b: f64, k: f64) -> f64 {
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b + (a - b) * h - k * h * (1.0 - h)
+}
+
+impl Shape for SdfShape {
+    // Marches from where the ray enters the bounds to where it leaves, or for
+    // max_distance when they are unbounded, with max_steps to spend on every
+    // max_distance of the range. Each time the ray reaches the surface it is
+    // stepped through the epsilon shell and the march goes on, so a solid
+    // reports both where the ray enters it and where it leaves, as a CSG
+    // operand needs. A ray that only grazes the surface reports a single hit.
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        let (entry, exit) = match self.bounds.intersection_range(ray) {
+            Some(range) => range,
+            None => return vec![]
+        };
+        let speed = ray.direction.magnitude();
+        let start = if entry.is_finite() { entry } else { 0.0 };
+        let end = if exit.is_finite() { exit } else { start + self.max_distance / speed };
+        let ranges = ((end - start) * speed / self.max_distance).ceil().max(1.0);
+        let max_steps = (self.max_steps as f64 * ranges) as usize;
+
+        let mut hits = vec![];
+        let mut on_surface = false;
+        let mut t = start;
+        for _ in 0..max_steps {
+            if t > end {
+                break;
+            }
+            let distance = (self.distance)(ray.position(t)).abs();
+            if distance < self.epsilon {
+                if !on_surface {
+                    hits.push(t);
+                    on_surface = true;
+                }
+                t += 2.0 * self.epsilon / speed;
+            } else {
+                on_surface = false;
+                t += distance / speed;
+            }
+        }
+        hits
+    }
+
+    fn local_normal_at(&self, point: Point3) -> Vector3 {
+        let h = self.epsilon;
+        let dx = Vector3::new(h, 0.0, 0.0);
+        let dy = Vector3::new(0.0, h, 0.0);
+        let dz = Vector3::new(0.0, 0.0, h);
+        Vector3::new(
+            (self.distance)(point + dx) - (self.distance)(point - dx),
+            (self.distance)(point + dy) - (self.distance)(point - dy),
+            (self.distance)(point + dz) - (self.distance)(point - dz)
+        ).normalize()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_sphere() -> SdfShape {
+        SdfShape::new(|p: Point3| (p - Point3::new(0.0, 0.0, 0.0)).magnitude() - 1.0)
+    }
+
+    #[test]
+    fn sphere_tracing_a_distance_field_sphere() {
+        let s = unit_sphere();
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = s.local_intersect(&r);
+
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0] - 4.0).abs() < s.epsilon);
+        assert!((hits[1] - 6.0).abs() < 3.0 * s.epsilon);
+    }
+
+    #[test]
+    fn sphere_tracing_with_an_unnormalized_direction() {
+        let s = unit_sphere();
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 2.0));
+        let hits = s.local_intersect(&r);
+
+        assert!((hits[0] - 2.0).abs() < s.epsilon);
+    }
+
+    #[test]
+    fn a_ray_misses_a_distance_field() {
+        let s = unit_sphere();
+        let r = Ray::new(Point3::new(0.0, 2.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(s.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn running_out_of_steps_is_a_miss() {
+        let mut s = unit_sphere();
+        let grazing = Ray::new(Point3::new(0.0, 0.99, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(s.local_intersect(&grazing).len(), 2);
+
+        s.max_steps = 2;
+        assert!(s.local_intersect(&grazing).is_empty());
+    }
+
+    #[test]
+    fn the_march_starts_where_the_ray_enters_the_bounds() {
+        let mut s = SdfShape::new(|p: Point3| (p - Point3::new(0.0, 0.0, 200.0)).magnitude() - 1.0);
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(s.local_intersect(&r).is_empty());
+
+        s.bounds = BoundingBox::new(Point3::new(-1.0, -1.0, 199.0), Point3::new(1.0, 1.0, 201.0));
+        let hits = s.local_intersect(&r);
+
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0] - 199.0).abs() < s.epsilon);
+        assert!((hits[1] - 201.0).abs() < 3.0 * s.epsilon);
+    }
+
+    #[test]
+    fn long_bounded_ranges_get_more_steps() {
+        // Underestimating the distance a hundredfold makes the march crawl.
+        let mut s = SdfShape::new(|p: Point3| 0.01 * ((p - Point3::new(0.0, 0.0, 0.0)).magnitude() - 1.0));
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(s.local_intersect(&r).is_empty());
+
+        s.bounds = BoundingBox::new(Point3::new(-1.0, -1.0, -5.0), Point3::new(1.0, 1.0, 995.0));
+        let hits = s.local_intersect(&r);
+
+        assert!((hits[0] - 4.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn normals_from_central_differences() {
+        let s = unit_sphere();
+        let n = s.local_normal_at(Point3::new(0.57735, 0.57735, 0.57735));
+
+        assert_eq!(n, Vector3::new(0.57735, 0.57735, 0.57735));
+        assert_eq!(s.local_normal_at(Point3::new(0.0, 0.0, -1.0)), Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn blending_two_spheres_fills_the_gap_between_them() {
+        let blob = SdfShape::new(|p: Point3| {
+            let a = (p - Point3::new(-1.1, 0.0, 0.0)).magnitude() - 1.0;
+            let b = (p - Point3::new(1.1, 0.0, 0.0)).magnitude() - 1.0;
+            smooth_min(a, b, 0.5)
+        });
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(blob.local_intersect(&r).len(), 2);
+        assert_eq!(smooth_min(0.0, 5.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn distance_fields_are_unbounded_by_default() {
+        assert!(!unit_sphere().bounds().is_finite());
+    }
+}
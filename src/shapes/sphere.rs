@@ -0,0 +1,76 @@
+use super::Shape;
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::roots::solve_quadratic;
+
+// The unit sphere centred on the origin; place and size it with an Instance
+// or a Group.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sphere;
+
+impl Sphere {
+    pub fn new() -> Sphere {
+        Sphere
+    }
+}
+
+impl Default for Sphere {
+    fn default() -> Self {
+        Sphere::new()
+    }
+}
+
+impl Shape for Sphere {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        let to_ray = ray.origin - Point3::new(0.0, 0.0, 0.0);
+        solve_quadratic(
+            ray.direction * ray.direction,
+            2.0 * (ray.direction * to_ray),
+            to_ray * to_ray - 1.0
+        )
+    }
+
+    fn local_normal_at(&self, point: Point3) -> Vector3 {
+        point - Point3::new(0.0, 0.0, 0.0)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_ray_intersects_a_sphere_at_two_points() {
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(Sphere::new().local_intersect(&r), vec![4.0, 6.0]);
+    }
+
+    #[test]
+    fn a_ray_from_inside_a_sphere_hits_behind_and_ahead() {
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(Sphere::new().local_intersect(&r), vec![-1.0, 1.0]);
+    }
+
+    #[test]
+    fn a_ray_misses_a_sphere() {
+        let r = Ray::new(Point3::new(0.0, 2.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(Sphere::new().local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn the_normal_on_a_sphere_points_away_from_the_centre() {
+        let k = 3.0_f64.sqrt() / 3.0;
+
+        assert_eq!(Sphere::new().local_normal_at(Point3::new(1.0, 0.0, 0.0)), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(Sphere::new().local_normal_at(Point3::new(k, k, k)), Vector3::new(k, k, k));
+    }
+}
@@ -0,0 +1,129 @@
+use super::Shape;
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::roots::solve_quartic;
+
+// A torus lying in the xz plane, centred on the origin and wrapped around
+// the y axis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Torus {
+    pub major_radius: f64,
+    pub minor_radius: f64
+}
+
+impl Torus {
+    pub fn new(major_radius: f64, minor_radius: f64) -> Torus {
+        Torus {
+            major_radius,
+            minor_radius
+        }
+    }
+}
+
+impl Shape for Torus {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        let o = ray.origin - Point3::new(0.0, 0.0, 0.0);
+        let d = ray.direction;
+        let major_squared = self.major_radius * self.major_radius;
+        let minor_squared = self.minor_radius * self.minor_radius;
+        let four_major_squared = 4.0 * major_squared;
+
+        let sum_d_squared = d * d;
+        let e = o * o - major_squared - minor_squared;
+        let f = o * d;
+
+        solve_quartic(
+            sum_d_squared * sum_d_squared,
+            4.0 * sum_d_squared * f,
+            2.0 * sum_d_squared * e + 4.0 * f * f + four_major_squared * d.1 * d.1,
+            4.0 * f * e + 2.0 * four_major_squared * o.1 * d.1,
+            e * e - four_major_squared * (minor_squared - o.1 * o.1)
+        )
+    }
+
+    fn local_normal_at(&self, point: Point3) -> Vector3 {
+        let p = point - Point3::new(0.0, 0.0, 0.0);
+        let major_squared = self.major_radius * self.major_radius;
+        let s = p * p - major_squared - self.minor_radius * self.minor_radius;
+        Vector3::new(
+            point.0 * s,
+            point.1 * (s + 2.0 * major_squared),
+            point.2 * s
+        ).normalize()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let outer = self.major_radius + self.minor_radius;
+        BoundingBox::new(
+            Point3::new(-outer, -self.minor_radius, -outer),
+            Point3::new(outer, self.minor_radius, outer)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx_equal::approx_equal::equal;
+
+    fn assert_hits(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "hits: {:?}", actual);
+        for (t, want) in actual.iter().zip(expected.iter()) {
+            assert!(equal(*t, *want), "hits: {:?}", actual);
+        }
+    }
+
+    #[test]
+    fn a_ray_through_the_hole_plane_hits_four_times() {
+        let t = Torus::new(1.0, 0.25);
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert_hits(t.local_intersect(&r), &[3.75, 4.25, 5.75, 6.25]);
+    }
+
+    #[test]
+    fn a_ray_down_through_the_tube_hits_twice() {
+        let t = Torus::new(1.0, 0.25);
+        let r = Ray::new(Point3::new(1.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        assert_hits(t.local_intersect(&r), &[4.75, 5.25]);
+    }
+
+    #[test]
+    fn a_ray_with_an_unnormalized_direction() {
+        let t = Torus::new(2.0, 0.5);
+        let r = Ray::new(Point3::new(0.0, 0.0, -10.0), Vector3::new(0.0, 0.0, 2.0));
+
+        assert_hits(t.local_intersect(&r), &[3.75, 4.25, 5.75, 6.25]);
+    }
+
+    #[test]
+    fn a_ray_misses_a_torus() {
+        let t = Torus::new(1.0, 0.25);
+        let through_hole = Ray::new(Point3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let above = Ray::new(Point3::new(-5.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert!(t.local_intersect(&through_hole).is_empty());
+        assert!(t.local_intersect(&above).is_empty());
+    }
+
+    #[test]
+    fn the_normal_on_a_torus() {
+        let t = Torus::new(1.0, 0.25);
+
+        assert_eq!(t.local_normal_at(Point3::new(1.25, 0.0, 0.0)), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(t.local_normal_at(Point3::new(0.75, 0.0, 0.0)), Vector3::new(-1.0, 0.0, 0.0));
+        assert_eq!(t.local_normal_at(Point3::new(1.0, 0.25, 0.0)), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(t.local_normal_at(Point3::new(0.0, -0.25, 1.0)), Vector3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn a_torus_has_bounds() {
+        let b = Torus::new(1.0, 0.25).bounds();
+
+        assert_eq!(b.min, Point3::new(-1.25, -0.25, -1.25));
+        assert_eq!(b.max, Point3::new(1.25, 0.25, 1.25));
+    }
+}
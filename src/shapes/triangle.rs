@@ -0,0 +1,228 @@
+use super::Shape;
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::intersection::Intersection;
+
+const EPSILON: f64 = 0.00001;
+
+// A flat triangle. Hits report barycentric u and v, where u weights p2 and
+// v weights p3 (so p1 has weight 1 - u - v).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Triangle {
+    p1: Point3,
+    p2: Point3,
+    p3: Point3,
+    e1: Vector3,
+    e2: Vector3,
+    normal: Vector3
+}
+
+impl Triangle {
+    pub fn new(p1: Point3, p2: Point3, p3: Point3) -> Triangle {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Triangle {
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e2.cross(&e1).normalize()
+        }
+    }
+
+    pub fn points(&self) -> [Point3; 3] {
+        [self.p1, self.p2, self.p3]
+    }
+
+    pub fn normal(&self) -> Vector3 {
+        self.normal
+    }
+
+    // Möller–Trumbore: t, u and v of the hit, if any.
+    fn hit(&self, ray: &Ray) -> Option<(f64, f64, f64)> {
+        let dir_cross_e2 = ray.direction.cross(&self.e2);
+        let det = self.e1 * dir_cross_e2;
+        if det.abs() < EPSILON {
+            return None;
+        }
+        let f = 1.0 / det;
+        let p1_to_origin = ray.origin - self.p1;
+        let u = f * (p1_to_origin * dir_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let origin_cross_e1 = p1_to_origin.cross(&self.e1);
+        let v = f * (ray.direction * origin_cross_e1);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        Some((f * (self.e2 * origin_cross_e1), u, v))
+    }
+
+    // u and v of a point already known to lie in the triangle's plane.
+    fn barycentric(&self, point: Point3) -> (f64, f64) {
+        let to_point = point - self.p1;
+        let (d11, d12, d22) = (self.e1 * self.e1, self.e1 * self.e2, self.e2 * self.e2);
+        let (d1p, d2p) = (self.e1 * to_point, self.e2 * to_point);
+        let denominator = d11 * d22 - d12 * d12;
+        ((d22 * d1p - d12 * d2p) / denominator, (d11 * d2p - d12 * d1p) / denominator)
+    }
+}
+
+impl Shape for Triangle {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        self.hit(ray).map(|(t, _, _)| t).into_iter().collect()
+    }
+
+    fn local_normal_at(&self, _point: Point3) -> Vector3 {
+        self.normal
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        for point in self.points().iter() {
+            bounds.add_point(*point);
+        }
+        bounds
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.hit(ray).map(|(t, u, v)| Intersection::with_uv(t, self, u, v)).into_iter().collect()
+    }
+}
+
+// A triangle with a normal per vertex, interpolated across the face so that
+// a coarse mesh shades as if it were curved.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SmoothTriangle {
+    triangle: Triangle,
+    normals: [Vector3; 3]
+}
+
+impl SmoothTriangle {
+    pub fn new(points: [Point3; 3], normals: [Vector3; 3]) -> SmoothTriangle {
+        SmoothTriangle {
+            triangle: Triangle::new(points[0], points[1], points[2]),
+            normals
+        }
+    }
+
+    pub fn points(&self) -> [Point3; 3] {
+        self.triangle.points()
+    }
+
+    pub fn normals(&self) -> [Vector3; 3] {
+        self.normals
+    }
+
+    fn interpolated_normal(&self, u: f64, v: f64) -> Vector3 {
+        (self.normals[1] * u + self.normals[2] * v + self.normals[0] * (1.0 - u - v)).normalize()
+    }
+}
+
+impl Shape for SmoothTriangle {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        self.triangle.local_intersect(ray)
+    }
+
+    // Without a hit to hand, u and v are recovered from the point itself.
+    fn local_normal_at(&self, point: Point3) -> Vector3 {
+        let (u, v) = self.triangle.barycentric(point);
+        self.interpolated_normal(u, v)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.triangle.bounds()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.triangle.hit(ray).map(|(t, u, v)| Intersection::with_uv(t, self, u, v)).into_iter().collect()
+    }
+
+    fn local_normal_at_hit(&self, _point: Point3, hit: &Intersection) -> Vector3 {
+        self.interpolated_normal(hit.u, hit.v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intersection::hit;
+
+    fn triangle() -> Triangle {
+        Triangle::new(Point3::new(0.0, 1.0, 0.0), Point3::new(-1.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0))
+    }
+
+    fn smooth_triangle() -> SmoothTriangle {
+        SmoothTriangle::new(
+            [Point3::new(0.0, 1.0, 0.0), Point3::new(-1.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0)],
+            [Vector3::new(0.0, 1.0, 0.0), Vector3::new(-1.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)]
+        )
+    }
+
+    #[test]
+    fn constructing_a_triangle() {
+        let t = triangle();
+
+        assert_eq!(t.normal(), Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(t.local_normal_at(Point3::new(-0.5, 0.75, 0.0)), t.normal());
+        assert_eq!(t.bounds().min, Point3::new(-1.0, 0.0, 0.0));
+        assert_eq!(t.bounds().max, Point3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn rays_that_miss_a_triangle() {
+        let t = triangle();
+        let parallel = Ray::new(Point3::new(0.0, -1.0, -2.0), Vector3::new(0.0, 1.0, 0.0));
+        let past_p1_p3 = Ray::new(Point3::new(1.0, 1.0, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let past_p1_p2 = Ray::new(Point3::new(-1.0, 1.0, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let past_p2_p3 = Ray::new(Point3::new(0.0, -1.0, -2.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(t.local_intersect(&parallel).is_empty());
+        assert!(t.local_intersect(&past_p1_p3).is_empty());
+        assert!(t.local_intersect(&past_p1_p2).is_empty());
+        assert!(t.local_intersect(&past_p2_p3).is_empty());
+    }
+
+    #[test]
+    fn a_ray_strikes_a_triangle() {
+        let t = triangle();
+        let r = Ray::new(Point3::new(0.0, 0.5, -2.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(t.local_intersect(&r), vec![2.0]);
+    }
+
+    #[test]
+    fn an_intersection_with_a_smooth_triangle_stores_u_and_v() {
+        let s = smooth_triangle();
+        let r = Ray::new(Point3::new(-0.2, 0.3, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = s.intersect(&r);
+
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].u - 0.45).abs() < EPSILON);
+        assert!((hits[0].v - 0.25).abs() < EPSILON);
+    }
+
+    #[test]
+    fn a_smooth_triangle_interpolates_the_normal() {
+        let s = smooth_triangle();
+        let i = Intersection::with_uv(1.0, &s, 0.45, 0.25);
+
+        assert_eq!(i.normal_at(Point3::new(0.0, 0.0, 0.0)), Vector3::new(-0.5547, 0.83205, 0.0));
+    }
+
+    #[test]
+    fn a_smooth_triangle_normal_from_a_point_matches_the_hit() {
+        let s = smooth_triangle();
+        let r = Ray::new(Point3::new(-0.2, 0.3, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let hits = s.intersect(&r);
+        let h = hit(&hits).unwrap();
+        let point = r.position(h.t);
+
+        assert_eq!(s.local_normal_at(point), h.normal_at(point));
+        assert_eq!(s.local_normal_at(point), Vector3::new(-0.5547, 0.83205, 0.0));
+    }
+}
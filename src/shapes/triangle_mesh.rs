@@ -0,0 +1,171 @@
+use super::{Shape, normal_through_hits};
+use super::triangle::{Triangle, SmoothTriangle};
+use crate::point3::Point3;
+use crate::vector3::Vector3;
+use crate::ray::Ray;
+use crate::bounding_box::BoundingBox;
+use crate::intersection::Intersection;
+use crate::bvh::Bvh;
+use crate::mesh::{Mesh, MeshGroup};
+
+// Every triangle of a mesh as one shape. A BVH over the triangle bounds
+// narrows each ray down to the few triangles it can actually reach, so large
+// models don't cost a test per triangle. Triangles that carry vertex normals
+// become smooth triangles.
+pub struct TriangleMesh {
+    triangles: Vec<Box<dyn Shape>>,
+    bvh: Bvh
+}
+
+impl TriangleMesh {
+    pub fn from_mesh(mesh: &Mesh) -> TriangleMesh {
+        let mut triangles = vec![];
+        collect_triangles(mesh, &mesh.root, &mut triangles);
+        let bounds: Vec<BoundingBox> = triangles.iter().map(|triangle| triangle.bounds()).collect();
+        TriangleMesh {
+            triangles,
+            bvh: Bvh::build(&bounds)
+        }
+    }
+
+    pub fn triangles(&self) -> &[Box<dyn Shape>] {
+        &self.triangles
+    }
+}
+
+fn collect_triangles(mesh: &Mesh, group: &MeshGroup, triangles: &mut Vec<Box<dyn Shape>>) {
+    for triangle in group.triangles.iter() {
+        let points = mesh.triangle_points(triangle);
+        let shape: Box<dyn Shape> = match triangle.normals {
+            Some([n1, n2, n3]) => Box::new(SmoothTriangle::new(
+                points,
+                [mesh.normals[n1], mesh.normals[n2], mesh.normals[n3]]
+            )),
+            None => Box::new(Triangle::new(points[0], points[1], points[2]))
+        };
+        triangles.push(shape);
+    }
+    for child in group.children.iter() {
+        collect_triangles(mesh, child, triangles);
+    }
+}
+
+impl Shape for TriangleMesh {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        self.intersect(ray).into_iter().map(|hit| hit.t).collect()
+    }
+
+    // Which triangle a point lies on is only known from a hit.
+    fn local_normal_at(&self, point: Point3) -> Vector3 {
+        normal_through_hits(self, point)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.bvh.bounds()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut hits: Vec<Intersection> = self.bvh.candidates(ray).into_iter()
+            .flat_map(|index| self.triangles[index].intersect(ray))
+            .collect();
+        hits.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::MeshTriangle;
+    use crate::mesh::subdivision::loop_subdivide;
+    use crate::mesh::normals::generate_normals;
+    use crate::intersection::hit;
+
+    fn octahedron() -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.vertices.push(Point3::new(1.0, 0.0, 0.0));
+        mesh.vertices.push(Point3::new(-1.0, 0.0, 0.0));
+        mesh.vertices.push(Point3::new(0.0, 1.0, 0.0));
+        mesh.vertices.push(Point3::new(0.0, -1.0, 0.0));
+        mesh.vertices.push(Point3::new(0.0, 0.0, 1.0));
+        mesh.vertices.push(Point3::new(0.0, 0.0, -1.0));
+        for &[a, b, c] in [
+            [0, 2, 4], [2, 1, 4], [1, 3, 4], [3, 0, 4],
+            [2, 0, 5], [1, 2, 5], [3, 1, 5], [0, 3, 5]
+        ].iter() {
+            mesh.root.triangles.push(MeshTriangle::new([a, b, c]));
+        }
+        mesh
+    }
+
+    fn brute_force_ts(mesh: &TriangleMesh, ray: &Ray) -> Vec<f64> {
+        let mut ts: Vec<f64> = mesh.triangles().iter()
+            .flat_map(|triangle| triangle.local_intersect(ray))
+            .collect();
+        ts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        ts
+    }
+
+    #[test]
+    fn every_mesh_triangle_becomes_a_shape() {
+        let mesh = TriangleMesh::from_mesh(&octahedron());
+        let b = mesh.bounds();
+
+        assert_eq!(mesh.triangles().len(), 8);
+        assert_eq!(b.min, Point3::new(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Point3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn the_bvh_finds_the_same_hits_as_brute_force() {
+        let mesh = TriangleMesh::from_mesh(&loop_subdivide(&octahedron(), 3));
+        assert_eq!(mesh.triangles().len(), 512);
+
+        let mut rays = 0;
+        let mut hit_rays = 0;
+        for i in 0..15 {
+            for j in 0..15 {
+                let x = -1.2 + 2.4 * i as f64 / 14.0;
+                let y = -1.2 + 2.4 * j as f64 / 14.0;
+                let straight = Ray::new(Point3::new(x, y, -5.0), Vector3::new(0.0, 0.0, 1.0));
+                let slanted = Ray::new(Point3::new(x, -4.0, y), Vector3::new(0.3, 1.0, -0.2));
+                for r in [straight, slanted].iter() {
+                    let ts: Vec<f64> = mesh.intersect(r).iter().map(|hit| hit.t).collect();
+                    assert_eq!(ts, brute_force_ts(&mesh, r));
+                    rays += 1;
+                    if !ts.is_empty() {
+                        hit_rays += 1;
+                    }
+                }
+            }
+        }
+        assert!(hit_rays > 0 && hit_rays < rays);
+    }
+
+    #[test]
+    fn a_ray_only_tests_a_fraction_of_the_triangles() {
+        let mesh = TriangleMesh::from_mesh(&loop_subdivide(&octahedron(), 3));
+        let r = Ray::new(Point3::new(0.1, 0.2, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(mesh.bvh.candidates(&r).len() < mesh.triangles().len() / 10);
+        assert_eq!(mesh.intersect(&r).len(), 2);
+    }
+
+    #[test]
+    fn meshes_with_normals_shade_smoothly() {
+        let faceted = loop_subdivide(&octahedron(), 2);
+        let flat = TriangleMesh::from_mesh(&faceted);
+        let smooth = TriangleMesh::from_mesh(&generate_normals(&faceted, std::f64::consts::PI));
+        let r = Ray::new(Point3::new(0.1, 0.2, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let (flat_hits, smooth_hits) = (flat.intersect(&r), smooth.intersect(&r));
+        let (flat_hit, smooth_hit) = (hit(&flat_hits).unwrap(), hit(&smooth_hits).unwrap());
+        let point = r.position(smooth_hit.t);
+        let n = smooth_hit.normal_at(point);
+
+        assert_eq!(flat_hit.t, smooth_hit.t);
+        assert!(n.2 < 0.0);
+        assert!(n != flat_hit.normal_at(point));
+        assert_eq!(smooth.local_normal_at(point), n);
+        assert_eq!(flat.local_normal_at(point), flat_hit.normal_at(point));
+    }
+}